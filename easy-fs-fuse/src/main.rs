@@ -0,0 +1,191 @@
+//! Pack the user apps into an easy-fs image on the host, the kernel loads them from it.
+//!
+//! usage: easy-fs-fuse -s <app source dir> -t <app elf dir> -o <image>
+//!
+//! An existing image is reused and only the apps are overwritten, so that the files
+//! created by the apps survive a rebuild.
+
+use std::{
+    fs::{read_dir, File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    sync::{Arc, Mutex},
+};
+
+use easy_fs::{block_cache_sync_all, BlockDevice, EasyFileSystem, BLOCK_SZ};
+
+/// size of the image, 16 MiB
+const TOTAL_BLOCKS: u32 = 16 * 2048;
+/// 4096 inodes
+const INODE_BITMAP_BLOCKS: u32 = 1;
+
+/// The image file seen as a block device
+struct BlockFile(Mutex<File>);
+
+impl BlockDevice for BlockFile {
+    fn read_block(&self, block_id: usize, buf: &mut [u8]) {
+        let mut file = self.0.lock().unwrap();
+        file.seek(SeekFrom::Start((block_id * BLOCK_SZ) as u64))
+            .expect("Error when seeking!");
+        assert_eq!(file.read(buf).unwrap(), BLOCK_SZ, "Not a complete block!");
+    }
+
+    fn write_block(&self, block_id: usize, buf: &[u8]) {
+        let mut file = self.0.lock().unwrap();
+        file.seek(SeekFrom::Start((block_id * BLOCK_SZ) as u64))
+            .expect("Error when seeking!");
+        assert_eq!(file.write(buf).unwrap(), BLOCK_SZ, "Not a complete block!");
+    }
+}
+
+struct Args {
+    source: String,
+    target: String,
+    image: String,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut source = None;
+    let mut target = None;
+    let mut image = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        let value = args.next().ok_or(format!("missing value of {}", flag))?;
+        match flag.as_str() {
+            "-s" => source = Some(value),
+            "-t" => target = Some(value),
+            "-o" => image = Some(value),
+            _ => return Err(format!("unknown option {}", flag)),
+        }
+    }
+    Ok(Args {
+        source: source.ok_or("missing -s <app source dir>")?,
+        target: target.ok_or("missing -t <app elf dir>")?,
+        image: image.ok_or("missing -o <image>")?,
+    })
+}
+
+fn main() {
+    let args = parse_args().unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        eprintln!("usage: easy-fs-fuse -s <app source dir> -t <app elf dir> -o <image>");
+        std::process::exit(1);
+    });
+    easy_fs_pack(&args).expect("Error when packing easy-fs!");
+}
+
+fn easy_fs_pack(args: &Args) -> std::io::Result<()> {
+    let block_file: Arc<dyn BlockDevice> = Arc::new(BlockFile(Mutex::new({
+        let f = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&args.image)?;
+        f.set_len((TOTAL_BLOCKS as usize * BLOCK_SZ) as u64)?;
+        f
+    })));
+    let efs = EasyFileSystem::open(block_file.clone())
+        .unwrap_or_else(|| EasyFileSystem::create(block_file, TOTAL_BLOCKS, INODE_BITMAP_BLOCKS));
+    let root_inode = EasyFileSystem::root_inode(&efs);
+    // every app has a source file src/bin/<app>.rs
+    let mut apps: Vec<String> = read_dir(&args.source)?
+        .map(|dir_entry| {
+            let mut name_with_ext = dir_entry.unwrap().file_name().into_string().unwrap();
+            name_with_ext.drain(name_with_ext.find('.').unwrap()..name_with_ext.len());
+            name_with_ext
+        })
+        .collect();
+    apps.sort();
+    for app in apps {
+        let mut elf_file = File::open(format!("{}/{}", args.target, app))?;
+        let mut all_data: Vec<u8> = Vec::new();
+        elf_file.read_to_end(&mut all_data)?;
+        let fs_error = |err| std::io::Error::other(format!("{}: {:?}", app, err));
+        let inode = match root_inode.find(app.as_str()) {
+            Some(inode) => inode,
+            None => root_inode.create(app.as_str()).map_err(fs_error)?,
+        };
+        inode.clear();
+        inode.write_at(0, all_data.as_slice()).map_err(fs_error)?;
+        println!("app: {} ({} bytes)", app, all_data.len());
+    }
+    // the cache is never dropped, write it back before exiting
+    block_cache_sync_all();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use easy_fs::{FsError, MAX_FILE_SIZE};
+
+    /// a 2 MiB disk in memory, far smaller than `MAX_FILE_SIZE`
+    struct RamDisk(Mutex<Vec<[u8; BLOCK_SZ]>>);
+
+    impl BlockDevice for RamDisk {
+        fn read_block(&self, block_id: usize, buf: &mut [u8]) {
+            buf.copy_from_slice(&self.0.lock().unwrap()[block_id]);
+        }
+
+        fn write_block(&self, block_id: usize, buf: &[u8]) {
+            self.0.lock().unwrap()[block_id].copy_from_slice(buf);
+        }
+    }
+
+    #[test]
+    fn efs_test() {
+        let total_blocks = 4096;
+        let ram_disk: Arc<dyn BlockDevice> =
+            Arc::new(RamDisk(Mutex::new(vec![[0u8; BLOCK_SZ]; total_blocks])));
+        EasyFileSystem::create(ram_disk.clone(), total_blocks as u32, 1);
+        let efs = EasyFileSystem::open(ram_disk).unwrap();
+        let root_inode = EasyFileSystem::root_inode(&efs);
+
+        // create, write and read back
+        let file_a = root_inode.create("filea").unwrap();
+        assert_eq!(root_inode.create("filea").err(), Some(FsError::Exists));
+        let greet = b"Hello, world!";
+        assert_eq!(file_a.write_at(0, greet), Ok(greet.len()));
+        let mut buffer = [0u8; 64];
+        assert_eq!(file_a.read_at(0, &mut buffer), greet.len());
+        assert_eq!(&buffer[..greet.len()], greet);
+        assert_eq!(root_inode.ls(), ["filea"]);
+        assert_eq!(root_inode.find("filea").unwrap().size(), greet.len());
+
+        // a file can't grow past the largest size, and nothing is written then
+        assert_eq!(file_a.write_at(MAX_FILE_SIZE, b"x"), Err(FsError::FileTooLarge));
+        assert_eq!(file_a.write_at(usize::MAX, b"x"), Err(FsError::FileTooLarge));
+        assert_eq!(file_a.size(), greet.len());
+
+        // fill the disk, what was written before it's full can be read back
+        let chunk: Vec<u8> = (0..8192).map(|i| (i % 251) as u8).collect();
+        let file_b = root_inode.create("fileb").unwrap();
+        let mut size = 0;
+        let err = loop {
+            match file_b.write_at(size, &chunk) {
+                Ok(len) => size += len,
+                Err(err) => break err,
+            }
+        };
+        assert_eq!(err, FsError::NoSpace);
+        assert!(size > 0 && size < total_blocks * BLOCK_SZ);
+        assert_eq!(file_b.size(), size);
+        let mut read_back = vec![0u8; chunk.len()];
+        for offset in (0..size).step_by(chunk.len()) {
+            assert_eq!(file_b.read_at(offset, &mut read_back), chunk.len());
+            assert_eq!(read_back, chunk);
+        }
+        // a whole chunk no longer fits, take the last blocks one by one
+        while file_b.write_at(file_b.size(), &chunk[..BLOCK_SZ]).is_ok() {}
+        // a file that needs one more block can't grow either
+        assert_eq!(file_a.write_at(BLOCK_SZ, b"x"), Err(FsError::NoSpace));
+        assert_eq!(file_a.size(), greet.len());
+
+        // the blocks of a cleared file can be used again
+        file_b.clear();
+        assert_eq!(file_b.size(), 0);
+        assert_eq!(file_b.write_at(0, &chunk), Ok(chunk.len()));
+        assert_eq!(file_a.write_at(BLOCK_SZ, b"x"), Ok(1));
+        block_cache_sync_all();
+    }
+}
@@ -0,0 +1,70 @@
+use alloc::sync::Arc;
+
+use super::{block_cache::get_block_cache, BlockDevice, BLOCK_SZ};
+
+/// a block of the bitmap is 4096 bits
+type BitmapBlock = [u64; 64];
+
+const BLOCK_BITS: usize = BLOCK_SZ * 8;
+
+/// A bitmap of `blocks` blocks starting from `start_block_id`, one bit per allocated item
+pub struct Bitmap {
+    start_block_id: usize,
+    blocks: usize,
+}
+
+/// split a bit number into (block, u64 in the block, bit in the u64)
+fn decomposition(mut bit: usize) -> (usize, usize, usize) {
+    let block_pos = bit / BLOCK_BITS;
+    bit %= BLOCK_BITS;
+    (block_pos, bit / 64, bit % 64)
+}
+
+impl Bitmap {
+    pub fn new(start_block_id: usize, blocks: usize) -> Self {
+        Self {
+            start_block_id,
+            blocks,
+        }
+    }
+
+    /// allocate the first free bit, return None if the bitmap is full
+    pub fn alloc(&self, block_device: &Arc<dyn BlockDevice>) -> Option<usize> {
+        for block_id in 0..self.blocks {
+            let pos = get_block_cache(
+                block_id + self.start_block_id,
+                Arc::clone(block_device),
+            )
+            .lock()
+            .modify(0, |bitmap_block: &mut BitmapBlock| {
+                let (bits64_pos, inner_pos) = bitmap_block
+                    .iter()
+                    .enumerate()
+                    .find(|(_, bits64)| **bits64 != u64::MAX)
+                    .map(|(bits64_pos, bits64)| (bits64_pos, bits64.trailing_ones() as usize))?;
+                bitmap_block[bits64_pos] |= 1u64 << inner_pos;
+                Some(block_id * BLOCK_BITS + bits64_pos * 64 + inner_pos)
+            });
+            if pos.is_some() {
+                return pos;
+            }
+        }
+        None
+    }
+
+    /// free an allocated bit
+    pub fn dealloc(&self, block_device: &Arc<dyn BlockDevice>, bit: usize) {
+        let (block_pos, bits64_pos, inner_pos) = decomposition(bit);
+        get_block_cache(block_pos + self.start_block_id, Arc::clone(block_device))
+            .lock()
+            .modify(0, |bitmap_block: &mut BitmapBlock| {
+                assert!(bitmap_block[bits64_pos] & (1u64 << inner_pos) > 0);
+                bitmap_block[bits64_pos] -= 1u64 << inner_pos;
+            });
+    }
+
+    /// the max number of items it can allocate
+    pub fn maximum(&self) -> usize {
+        self.blocks * BLOCK_BITS
+    }
+}
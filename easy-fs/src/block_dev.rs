@@ -0,0 +1,7 @@
+use core::any::Any;
+
+/// A device that is read and written in blocks of `BLOCK_SZ` bytes
+pub trait BlockDevice: Send + Sync + Any {
+    fn read_block(&self, block_id: usize, buf: &mut [u8]);
+    fn write_block(&self, block_id: usize, buf: &[u8]);
+}
@@ -0,0 +1,32 @@
+//! An easy inode-based file system, the layout on disk is
+//! superblock | inode bitmap | inodes | data bitmap | data blocks
+#![no_std]
+
+extern crate alloc;
+
+mod bitmap;
+mod block_cache;
+mod block_dev;
+mod efs;
+mod layout;
+mod vfs;
+
+/// every block is 512 bytes, the same as a sector of virtio-blk
+pub const BLOCK_SZ: usize = 512;
+
+/// why an operation on an inode failed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FsError {
+    /// the file to create is already there
+    Exists,
+    /// no free inode or data block is left
+    NoSpace,
+    /// the file would grow past `MAX_FILE_SIZE`
+    FileTooLarge,
+}
+
+pub use block_cache::block_cache_sync_all;
+pub use block_dev::BlockDevice;
+pub use efs::EasyFileSystem;
+pub use layout::MAX_FILE_SIZE;
+pub use vfs::Inode;
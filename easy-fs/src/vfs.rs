@@ -0,0 +1,191 @@
+use alloc::{string::String, sync::Arc, vec::Vec};
+use spin::{Mutex, MutexGuard};
+
+use super::{
+    block_cache::get_block_cache,
+    layout::{DirEntry, DiskInode, DiskInodeType, DIRENT_SZ, MAX_FILE_SIZE},
+    BlockDevice, EasyFileSystem, FsError,
+};
+
+/// An inode in memory, it only remembers where the disk inode is.
+/// Changes stay in the block cache until they are evicted or `block_cache_sync_all` is called
+pub struct Inode {
+    block_id: usize,
+    block_offset: usize,
+    fs: Arc<Mutex<EasyFileSystem>>,
+    block_device: Arc<dyn BlockDevice>,
+}
+
+impl Inode {
+    pub fn new(
+        block_id: u32,
+        block_offset: usize,
+        fs: Arc<Mutex<EasyFileSystem>>,
+        block_device: Arc<dyn BlockDevice>,
+    ) -> Self {
+        Self {
+            block_id: block_id as usize,
+            block_offset,
+            fs,
+            block_device,
+        }
+    }
+
+    fn read_disk_inode<V>(&self, f: impl FnOnce(&DiskInode) -> V) -> V {
+        get_block_cache(self.block_id, Arc::clone(&self.block_device))
+            .lock()
+            .read(self.block_offset, f)
+    }
+
+    fn modify_disk_inode<V>(&self, f: impl FnOnce(&mut DiskInode) -> V) -> V {
+        get_block_cache(self.block_id, Arc::clone(&self.block_device))
+            .lock()
+            .modify(self.block_offset, f)
+    }
+
+    /// look up `name` in a directory inode
+    fn find_inode_id(&self, name: &str, disk_inode: &DiskInode) -> Option<u32> {
+        assert!(disk_inode.is_dir());
+        let file_count = (disk_inode.size as usize) / DIRENT_SZ;
+        let mut dirent = DirEntry::empty();
+        for i in 0..file_count {
+            assert_eq!(
+                disk_inode.read_at(DIRENT_SZ * i, dirent.as_bytes_mut(), &self.block_device),
+                DIRENT_SZ,
+            );
+            if dirent.name() == name {
+                return Some(dirent.inode_number());
+            }
+        }
+        None
+    }
+
+    fn inode_of(&self, fs: &MutexGuard<EasyFileSystem>, inode_id: u32) -> Arc<Inode> {
+        let (block_id, block_offset) = fs.get_disk_inode_pos(inode_id);
+        Arc::new(Self::new(
+            block_id,
+            block_offset,
+            self.fs.clone(),
+            self.block_device.clone(),
+        ))
+    }
+
+    /// find a file in this directory by name
+    pub fn find(&self, name: &str) -> Option<Arc<Inode>> {
+        let fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| self.find_inode_id(name, disk_inode))
+            .map(|inode_id| self.inode_of(&fs, inode_id))
+    }
+
+    /// allocate the blocks needed to grow to `new_size`, the inode is left as it was on failure
+    fn increase_size(
+        &self,
+        new_size: usize,
+        disk_inode: &mut DiskInode,
+        fs: &mut MutexGuard<EasyFileSystem>,
+    ) -> Result<(), FsError> {
+        if new_size > MAX_FILE_SIZE {
+            return Err(FsError::FileTooLarge);
+        }
+        let new_size = new_size as u32;
+        if new_size < disk_inode.size {
+            return Ok(());
+        }
+        let blocks_needed = disk_inode.blocks_num_needed(new_size);
+        let mut v: Vec<u32> = Vec::with_capacity(blocks_needed as usize);
+        for _ in 0..blocks_needed {
+            let Some(block_id) = fs.alloc_data() else {
+                v.into_iter().for_each(|block_id| fs.dealloc_data(block_id));
+                return Err(FsError::NoSpace);
+            };
+            v.push(block_id);
+        }
+        disk_inode.increase_size(new_size, v, &self.block_device);
+        Ok(())
+    }
+
+    /// create a regular file in this directory
+    pub fn create(&self, name: &str) -> Result<Arc<Inode>, FsError> {
+        let mut fs = self.fs.lock();
+        if self
+            .read_disk_inode(|root_inode| self.find_inode_id(name, root_inode))
+            .is_some()
+        {
+            return Err(FsError::Exists);
+        }
+        // create a new file
+        let new_inode_id = fs.alloc_inode().ok_or(FsError::NoSpace)?;
+        let (new_inode_block_id, new_inode_block_offset) = fs.get_disk_inode_pos(new_inode_id);
+        get_block_cache(new_inode_block_id as usize, Arc::clone(&self.block_device))
+            .lock()
+            .modify(new_inode_block_offset, |new_inode: &mut DiskInode| {
+                new_inode.initialize(DiskInodeType::File);
+            });
+        // append a dirent to the directory
+        let appended = self.modify_disk_inode(|root_inode| {
+            let file_count = (root_inode.size as usize) / DIRENT_SZ;
+            let new_size = (file_count + 1) * DIRENT_SZ;
+            self.increase_size(new_size, root_inode, &mut fs)?;
+            let dirent = DirEntry::new(name, new_inode_id);
+            root_inode.write_at(file_count * DIRENT_SZ, dirent.as_bytes(), &self.block_device);
+            Ok(())
+        });
+        if let Err(err) = appended {
+            fs.dealloc_inode(new_inode_id);
+            return Err(err);
+        }
+        Ok(self.inode_of(&fs, new_inode_id))
+    }
+
+    /// names of all the files in this directory
+    pub fn ls(&self) -> Vec<String> {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| {
+            let file_count = (disk_inode.size as usize) / DIRENT_SZ;
+            let mut v: Vec<String> = Vec::new();
+            for i in 0..file_count {
+                let mut dirent = DirEntry::empty();
+                assert_eq!(
+                    disk_inode.read_at(i * DIRENT_SZ, dirent.as_bytes_mut(), &self.block_device),
+                    DIRENT_SZ,
+                );
+                v.push(String::from(dirent.name()));
+            }
+            v
+        })
+    }
+
+    /// read from `offset`, return the number of bytes read, 0 at the end of the file
+    pub fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| disk_inode.read_at(offset, buf, &self.block_device))
+    }
+
+    /// write at `offset`, the file grows if needed. Nothing is written if it can't grow
+    pub fn write_at(&self, offset: usize, buf: &[u8]) -> Result<usize, FsError> {
+        let end = offset.checked_add(buf.len()).ok_or(FsError::FileTooLarge)?;
+        let mut fs = self.fs.lock();
+        self.modify_disk_inode(|disk_inode| {
+            self.increase_size(end, disk_inode, &mut fs)?;
+            Ok(disk_inode.write_at(offset, buf, &self.block_device))
+        })
+    }
+
+    /// truncate the file to 0 bytes and free its blocks
+    pub fn clear(&self) {
+        let mut fs = self.fs.lock();
+        self.modify_disk_inode(|disk_inode| {
+            let size = disk_inode.size;
+            let data_blocks_dealloc = disk_inode.clear_size(&self.block_device);
+            assert!(data_blocks_dealloc.len() == DiskInode::total_blocks(size) as usize);
+            for data_block in data_blocks_dealloc.into_iter() {
+                fs.dealloc_data(data_block);
+            }
+        });
+    }
+
+    /// size of the file in bytes
+    pub fn size(&self) -> usize {
+        self.read_disk_inode(|disk_inode| disk_inode.size as usize)
+    }
+}
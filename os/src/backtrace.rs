@@ -0,0 +1,98 @@
+//! Kernel stack backtraces by walking the frame pointers, the kernel is built with
+//! `-Cforce-frame-pointers=yes`. Each frame keeps ra at fp - 8 and the caller's fp at fp - 16.
+
+use core::arch::asm;
+
+use crate::{
+    config::{BOOT_STACK_SIZE, KERNEL_STACK_SIZE, PAGE_SIZE, TRAMPOLINE},
+    println_unlocked,
+};
+
+/// size of the space reserved for the symbol table
+const KSYMS_SIZE: usize = 0x8_0000;
+/// frames deeper than this are not shown
+const MAX_DEPTH: usize = 32;
+
+/// Text symbols as "<hex address> <name>\n" lines sorted by address, padded with 0.
+/// The makefile fills it with `rust-objcopy --update-section` after linking,
+/// it stays empty with a plain `cargo build` and only addresses are printed then
+#[used]
+#[unsafe(link_section = ".ksyms")]
+static KSYMS: [u8; KSYMS_SIZE] = [0; KSYMS_SIZE];
+
+/// read through the linker symbols, the compiler would take the table as all zeros otherwise
+fn ksyms() -> &'static [u8] {
+    unsafe extern "C" {
+        fn sksyms();
+        fn eksyms();
+    }
+    let table = unsafe {
+        core::slice::from_raw_parts(sksyms as usize as *const u8, eksyms as usize - sksyms as usize)
+    };
+    let len = table.iter().position(|&b| b == 0).unwrap_or(table.len());
+    &table[..len]
+}
+
+/// the symbol containing `pc` and the offset of `pc` in it
+fn lookup(pc: usize) -> Option<(&'static str, usize)> {
+    let mut found = None;
+    for line in ksyms().split(|&b| b == b'\n') {
+        let Some(space) = line.iter().position(|&b| b == b' ') else {
+            continue;
+        };
+        let Some(addr) = core::str::from_utf8(&line[..space])
+            .ok()
+            .and_then(|addr| usize::from_str_radix(addr, 16).ok())
+        else {
+            continue;
+        };
+        if addr > pc {
+            break;
+        }
+        found = core::str::from_utf8(&line[space + 1..]).ok().map(|name| (name, pc - addr));
+    }
+    found
+}
+
+/// [bottom, top) of the stack `sp` is on, a boot stack or the kernel stack of a task
+fn stack_bounds(sp: usize) -> (usize, usize) {
+    unsafe extern "C" {
+        fn boot_stack_lower_bound();
+        fn boot_stack_top();
+    }
+    let lower = boot_stack_lower_bound as usize;
+    if (lower..boot_stack_top as usize).contains(&sp) {
+        let bottom = lower + (sp - lower) / BOOT_STACK_SIZE * BOOT_STACK_SIZE;
+        return (bottom, bottom + BOOT_STACK_SIZE);
+    }
+    // the layout of `kernel_stack_position`, a guard page below each stack
+    let stride = KERNEL_STACK_SIZE + PAGE_SIZE;
+    let top = TRAMPOLINE - (TRAMPOLINE - sp) / stride * stride;
+    (top - KERNEL_STACK_SIZE, top)
+}
+
+/// print the return addresses on the current stack, the frames without frame pointers
+/// (those in `core`) may end the walk early. It's printed for a panic, so STDOUT isn't taken
+pub fn print_backtrace() {
+    let (mut fp, sp): (usize, usize);
+    unsafe {
+        asm!("mv {}, s0", "mv {}, sp", out(reg) fp, out(reg) sp);
+    }
+    let (bottom, top) = stack_bounds(sp);
+    println_unlocked!("backtrace:");
+    for depth in 0..MAX_DEPTH {
+        if fp % 8 != 0 || fp < bottom + 16 || fp > top {
+            break;
+        }
+        let (ra, prev_fp) = unsafe { (*((fp - 8) as *const usize), *((fp - 16) as *const usize)) };
+        // ra is past the call, it may be the start of the next function after a noreturn call
+        match lookup(ra - 1).map(|(name, offset)| (name, offset + 1)) {
+            Some((name, offset)) => println_unlocked!("  #{} {:#x} {}+{:#x}", depth, ra, name, offset),
+            None => println_unlocked!("  #{} {:#x}", depth, ra),
+        }
+        if prev_fp <= fp {
+            break;
+        }
+        fp = prev_fp;
+    }
+}
@@ -0,0 +1,173 @@
+//! The machine found from the device tree that SBI passes in a1.
+//! The defaults are those of QEMU virt with 128 MiB of RAM, used when a node is missing.
+
+use alloc::{vec, vec::Vec};
+use fdt::{node::FdtNode, Fdt};
+use lazy_static::lazy_static;
+
+use crate::{config::MAX_HARTS, println, sync::SpinNoIrqLock};
+
+/// A device on the MMIO bus
+#[derive(Clone, Copy, Debug)]
+pub struct MmioDevice {
+    pub base: usize,
+    pub size: usize,
+    /// the interrupt source number of the PLIC
+    pub irq: Option<usize>,
+}
+
+pub struct BoardInfo {
+    pub memory_start: usize,
+    pub memory_end: usize,
+    /// the frequency of the time CSR
+    pub clock_freq: usize,
+    pub hart_ids: Vec<usize>,
+    pub uart: Option<MmioDevice>,
+    pub plic: Option<MmioDevice>,
+    /// all the virtio-mmio slots, most of them are empty
+    pub virtio: Vec<MmioDevice>,
+    /// the test device to power off and the RTC
+    pub misc: Vec<MmioDevice>,
+    /// [start, end) of the initramfs
+    pub initrd: Option<(usize, usize)>,
+}
+
+impl BoardInfo {
+    fn qemu_virt() -> Self {
+        let device = |base, size, irq| MmioDevice { base, size, irq };
+        Self {
+            memory_start: 0x8000_0000,
+            memory_end: 0x8800_0000,
+            clock_freq: 10_000_000,
+            hart_ids: vec![0],
+            uart: Some(device(0x1000_0000, 0x100, Some(10))),
+            plic: Some(device(0x0c00_0000, 0x60_0000, None)),
+            virtio: (0..8).map(|i| device(0x1000_1000 + i * 0x1000, 0x1000, Some(1 + i))).collect(),
+            misc: vec![device(0x10_0000, 0x1000, None), device(0x10_1000, 0x1000, Some(11))],
+            initrd: None,
+        }
+    }
+}
+
+lazy_static! {
+    static ref BOARD: SpinNoIrqLock<BoardInfo> = SpinNoIrqLock::new("BOARD", BoardInfo::qemu_virt());
+}
+
+fn mmio_device(node: &FdtNode) -> Option<MmioDevice> {
+    let region = node.reg()?.next()?;
+    Some(MmioDevice {
+        base: region.starting_address as usize,
+        size: region.size?,
+        irq: node.interrupts().and_then(|mut irqs| irqs.next()),
+    })
+}
+
+fn is_compatible(node: &FdtNode, compatibles: &[&str]) -> bool {
+    node.compatible()
+        .is_some_and(|compatible| compatible.all().any(|c| compatibles.contains(&c)))
+}
+
+/// parse the device tree at `dtb`, the defaults are kept if it's invalid.
+/// It has to run after the heap is initialized and before the frames holding the device tree are allocated.
+pub fn init(dtb: usize) {
+    let Ok(fdt) = (unsafe { Fdt::from_ptr(dtb as *const u8) }) else {
+        println!("[kernel] invalid device tree at {:#x}, assume QEMU virt", dtb);
+        return;
+    };
+    // only the sizes are kept from the defaults
+    let mut board = BoardInfo {
+        hart_ids: Vec::new(),
+        uart: None,
+        plic: None,
+        virtio: Vec::new(),
+        misc: Vec::new(),
+        ..BoardInfo::qemu_virt()
+    };
+    if let Some(region) = fdt.memory().regions().next() {
+        board.memory_start = region.starting_address as usize;
+        if let Some(size) = region.size {
+            board.memory_end = board.memory_start + size;
+        }
+    }
+    for cpu in fdt.cpus() {
+        board.clock_freq = cpu.timebase_frequency();
+        board.hart_ids.push(cpu.ids().first());
+    }
+    for node in fdt.all_nodes() {
+        if is_compatible(&node, &["ns16550a"]) {
+            board.uart = board.uart.or(mmio_device(&node));
+        } else if is_compatible(&node, &["riscv,plic0", "sifive,plic-1.0.0"]) {
+            board.plic = mmio_device(&node);
+        } else if is_compatible(&node, &["virtio,mmio"]) {
+            board.virtio.extend(mmio_device(&node));
+        } else if is_compatible(&node, &["sifive,test0", "google,goldfish-rtc"]) {
+            board.misc.extend(mmio_device(&node));
+        }
+    }
+    // virtio slots are listed from the last one
+    board.virtio.sort_by_key(|device| device.base);
+    if let Some(chosen) = fdt.find_node("/chosen") {
+        let start = chosen.property("linux,initrd-start").and_then(|prop| prop.as_usize());
+        let end = chosen.property("linux,initrd-end").and_then(|prop| prop.as_usize());
+        board.initrd = start.zip(end);
+    }
+    println!(
+        "[kernel] memory [{:#x}, {:#x}), timebase {} Hz, {} harts",
+        board.memory_start,
+        board.memory_end,
+        board.clock_freq,
+        board.hart_ids.len(),
+    );
+    println!("[kernel] uart {:x?}, plic {:x?}, {} virtio slots", board.uart, board.plic, board.virtio.len());
+    crate::log::set_clock_freq(board.clock_freq);
+    *BOARD.lock() = board;
+}
+
+pub fn memory_end() -> usize {
+    BOARD.lock().memory_end
+}
+
+pub fn clock_freq() -> usize {
+    BOARD.lock().clock_freq
+}
+
+pub fn hart_ids() -> Vec<usize> {
+    BOARD.lock().hart_ids.clone()
+}
+
+/// the harts the kernel runs on, one bit for each
+pub fn hart_mask() -> usize {
+    hart_ids()
+        .into_iter()
+        .filter(|&id| id < MAX_HARTS)
+        .fold(0, |mask, id| mask | 1 << id)
+}
+
+pub fn uart() -> Option<MmioDevice> {
+    BOARD.lock().uart
+}
+
+pub fn plic() -> Option<MmioDevice> {
+    BOARD.lock().plic
+}
+
+pub fn virtio_devices() -> Vec<MmioDevice> {
+    BOARD.lock().virtio.clone()
+}
+
+pub fn initrd() -> Option<(usize, usize)> {
+    BOARD.lock().initrd
+}
+
+/// all the devices to be mapped in kernel space
+pub fn mmio_devices() -> Vec<MmioDevice> {
+    let board = BOARD.lock();
+    board
+        .uart
+        .iter()
+        .chain(board.plic.iter())
+        .chain(board.virtio.iter())
+        .chain(board.misc.iter())
+        .copied()
+        .collect()
+}
@@ -1,22 +1,21 @@
 pub const USER_STACK_SIZE: usize = 4096;
+/// the max number of file descriptors a task can open
+pub const MAX_FD: usize = 128;
 pub const KERNEL_STACK_SIZE: usize = 4096 * 2;
+/// harts beyond this are left parked, `entry.asm` reserves a boot stack for each of the others
+pub const MAX_HARTS: usize = 4;
+/// the boot stack of each hart
+pub const BOOT_STACK_SIZE: usize = 4096 * 16;
 pub const KERNEL_HEAP_SIZE: usize = 0x30_0000;
-pub const MAX_APP_NUM: usize = 6;
-pub const APP_BASE_ADDRESS: usize = 0x80400000;
-pub const APP_SIZE_LIMIT: usize = 0x20000;
-
-pub const CLOCK_FREQ: usize = 12500000;
 
 pub const PAGE_SIZE: usize = 0x1000; // 4KB as page size
 pub const PAGE_SIZE_BITS: usize = 0xc; // 4KB -> 12 bits to represent
 
-pub const MEMORY_END: usize = 0x8800_0000;
+/// user space window that anonymous mmap areas are placed in
+pub const MMAP_BASE: usize = 0x10_0000_0000;
+pub const MMAP_TOP: usize = 0x20_0000_0000;
 
 pub const TRAMPOLINE: usize = usize::MAX - PAGE_SIZE + 1;
 pub const TRAP_CONTEXT: usize = TRAMPOLINE - PAGE_SIZE;
 
-pub const MMIO: &[(usize, usize)] = &[
-    (0x0010_0000, 0x00_2000), // VIRT_TEST/RTC  in virt machine
-];
-
 
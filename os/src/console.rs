@@ -1,8 +1,11 @@
-use crate::sbi::console_putchar;
+use crate::{sbi::console_putchar, sync::SpinNoIrqLock};
 use core::fmt::{self, Write};
 
 struct Stdout;
 
+/// keep the lines printed by different harts from interleaving
+static STDOUT: SpinNoIrqLock<Stdout> = SpinNoIrqLock::new("STDOUT", Stdout);
+
 impl Write for Stdout {
     fn write_str(&mut self, s: &str) -> fmt::Result {
         for c in s.chars() {
@@ -13,7 +16,13 @@ impl Write for Stdout {
 }
 
 pub fn print(args: fmt::Arguments) {
-    Stdout.write_fmt(args).unwrap();
+    STDOUT.lock().write_fmt(args).unwrap();
+}
+
+/// print without taking STDOUT, for a panic which may happen while it's held.
+/// The lines printed by other harts may interleave with it
+pub fn print_unlocked(args: fmt::Arguments) {
+    let _ = Stdout.write_fmt(args);
 }
 
 #[macro_export]
@@ -28,4 +37,12 @@ macro_rules! println {
     ($fmt: literal $(, $($arg: tt)+)?) => {
         $crate::console::print(format_args!(concat!($fmt, "\n") $(, $($arg)+)?));
     };
-}
\ No newline at end of file
+}
+
+/// `println!` through `print_unlocked`
+#[macro_export]
+macro_rules! println_unlocked {
+    ($fmt: literal $(, $($arg: tt)+)?) => {
+        $crate::console::print_unlocked(format_args!(concat!($fmt, "\n") $(, $($arg)+)?));
+    };
+}
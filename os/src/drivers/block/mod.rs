@@ -0,0 +1,13 @@
+mod virtio_blk;
+
+use alloc::sync::Arc;
+use lazy_static::lazy_static;
+
+pub use virtio_blk::VirtIOBlock;
+
+type BlockDeviceImpl = VirtIOBlock;
+
+lazy_static! {
+    /// the disk of the file system, the first virtio device on the MMIO bus
+    pub static ref BLOCK_DEVICE: Option<Arc<BlockDeviceImpl>> = BlockDeviceImpl::new().map(Arc::new);
+}
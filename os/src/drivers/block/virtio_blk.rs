@@ -0,0 +1,114 @@
+use alloc::vec::Vec;
+use easy_fs::BlockDevice;
+use lazy_static::lazy_static;
+use virtio_drivers::{Hal, VirtIOBlk, VirtIOHeader};
+
+use crate::{
+    board,
+    mm::{frame_alloc, FrameTracker, PhysAddr, PhysPageNum, VirtAddr, KERNEL_SPACE},
+    sync::SpinNoIrqLock,
+};
+
+/// The first virtio-blk device on the MMIO bus
+pub struct VirtIOBlock {
+    base: usize,
+    blk: SpinNoIrqLock<VirtIOBlk<'static, VirtioHal>>,
+}
+
+// SAFETY: the queues and the MMIO registers are only touched with `blk` locked
+unsafe impl Send for VirtIOBlock {}
+unsafe impl Sync for VirtIOBlock {}
+
+lazy_static! {
+    /// frames of the virtqueues, they are freed by `dma_dealloc`
+    static ref QUEUE_FRAMES: SpinNoIrqLock<Vec<FrameTracker>> =
+        SpinNoIrqLock::new("QUEUE_FRAMES", Vec::new());
+}
+
+impl BlockDevice for VirtIOBlock {
+    fn read_block(&self, block_id: usize, buf: &mut [u8]) {
+        self.blk
+            .lock()
+            .read_block(block_id, buf)
+            .expect("Error when reading VirtIOBlk");
+    }
+
+    fn write_block(&self, block_id: usize, buf: &[u8]) {
+        self.blk
+            .lock()
+            .write_block(block_id, buf)
+            .expect("Error when writing VirtIOBlk");
+    }
+}
+
+/// "virt" in little endian
+const VIRTIO_MMIO_MAGIC: u32 = 0x7472_6976;
+const VIRTIO_DEVICE_ID_BLOCK: u32 = 2;
+
+impl VirtIOBlock {
+    /// None if QEMU is run without a disk, all the virtio slots are empty then
+    pub fn new() -> Option<Self> {
+        let base = board::virtio_devices().into_iter().map(|device| device.base).find(|&base| {
+            let (magic, device_id) = unsafe {
+                (
+                    (base as *const u32).read_volatile(),
+                    ((base + 0x8) as *const u32).read_volatile(),
+                )
+            };
+            magic == VIRTIO_MMIO_MAGIC && device_id == VIRTIO_DEVICE_ID_BLOCK
+        })?;
+        unsafe {
+            let blk = VirtIOBlk::<VirtioHal>::new(&mut *(base as *mut VirtIOHeader)).ok()?;
+            Some(Self {
+                base,
+                blk: SpinNoIrqLock::new("VIRTIO_BLK", blk),
+            })
+        }
+    }
+
+    /// the number of 512-byte sectors, read from the device config space
+    pub fn capacity(&self) -> usize {
+        unsafe { ((self.base + 0x100) as *const u64).read_volatile() as usize }
+    }
+}
+
+pub struct VirtioHal;
+
+impl Hal for VirtioHal {
+    /// the pages must be physically contiguous, which holds as no one else allocates at the same time
+    fn dma_alloc(pages: usize) -> usize {
+        let mut queue_frames = QUEUE_FRAMES.lock();
+        let frames: Vec<FrameTracker> = (0..pages).map(|_| frame_alloc().unwrap()).collect();
+        let ppn_base = frames[0].ppn;
+        for (i, frame) in frames.iter().enumerate() {
+            assert_eq!(frame.ppn.0, ppn_base.0 + i);
+        }
+        queue_frames.extend(frames);
+        PhysAddr::from(ppn_base).0
+    }
+
+    fn dma_dealloc(pa: usize, pages: usize) -> i32 {
+        let start = PhysAddr::from(pa).floor().0;
+        // dropping the trackers gives the frames back
+        QUEUE_FRAMES
+            .lock()
+            .retain(|frame| !(start..start + pages).contains(&frame.ppn.0));
+        0
+    }
+
+    /// physical memory is identically mapped in kernel space
+    fn phys_to_virt(addr: usize) -> usize {
+        addr
+    }
+
+    /// buffers may be on a kernel stack, which isn't identically mapped
+    fn virt_to_phys(vaddr: usize) -> usize {
+        let va = VirtAddr::from(vaddr);
+        let ppn: PhysPageNum = KERNEL_SPACE
+            .lock()
+            .translate(va.floor())
+            .unwrap()
+            .ppn();
+        PhysAddr::from(ppn).0 + va.page_offset()
+    }
+}
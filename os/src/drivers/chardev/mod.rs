@@ -0,0 +1,26 @@
+mod ns16550a;
+
+use alloc::sync::Arc;
+use lazy_static::lazy_static;
+
+pub use ns16550a::NS16550a;
+
+use crate::board;
+
+/// A byte stream device driven by interrupts
+pub trait CharDevice {
+    /// block until a byte is received, None if a signal interrupts the wait
+    fn read(&self) -> Option<u8>;
+    /// take a received byte without blocking
+    fn try_read(&self) -> Option<u8>;
+    fn write(&self, ch: u8);
+    fn handle_irq(&self);
+}
+
+type CharDeviceImpl = NS16550a;
+
+lazy_static! {
+    /// the console UART found in the device tree
+    pub static ref UART: Option<Arc<CharDeviceImpl>> =
+        board::uart().map(|uart| Arc::new(CharDeviceImpl::new(uart.base)));
+}
@@ -0,0 +1,125 @@
+use alloc::collections::VecDeque;
+
+use crate::{
+    fs::interrupt_foreground,
+    sync::SpinNoIrqLock,
+    task::{block_current_and_run_next, current_task, mark_current_blocked, wakeup_task, WaitQueue},
+};
+
+use super::CharDevice;
+
+// registers, one byte apart on QEMU virt
+const RBR: usize = 0; // receive buffer, read
+const THR: usize = 0; // transmit holding, write
+const IER: usize = 1;
+const FCR: usize = 2;
+const LCR: usize = 3;
+const MCR: usize = 4;
+const LSR: usize = 5;
+
+const IER_RX_AVAILABLE: u8 = 1;
+/// enable the FIFOs and clear them
+const FCR_FIFO_RESET: u8 = 0x07;
+/// 8 data bits, no parity, 1 stop bit
+const LCR_8N1: u8 = 0x03;
+/// the interrupt line is only connected with OUT2 set
+const MCR_OUT2: u8 = 0x08;
+const LSR_DATA_READY: u8 = 1;
+const LSR_THR_EMPTY: u8 = 1 << 5;
+/// the byte sent by Ctrl-C
+const ETX: u8 = 0x03;
+
+/// bytes received but not read yet, the newer ones are dropped when it's full
+const BUFFER_SIZE: usize = 4096;
+
+/// The NS16550A UART of the console, received bytes are buffered by the interrupt handler
+pub struct NS16550a {
+    base: usize,
+    inner: SpinNoIrqLock<NS16550aInner>,
+}
+
+struct NS16550aInner {
+    buffer: VecDeque<u8>,
+    /// readers blocked on an empty buffer
+    read_waiters: WaitQueue,
+}
+
+impl NS16550a {
+    /// the baud rate is already set by SBI, only the receive interrupt is enabled here
+    pub fn new(base: usize) -> Self {
+        let uart = Self {
+            base,
+            inner: SpinNoIrqLock::new(
+                "UART",
+                NS16550aInner {
+                    buffer: VecDeque::with_capacity(BUFFER_SIZE),
+                    read_waiters: WaitQueue::new(),
+                },
+            ),
+        };
+        uart.write_reg(IER, 0);
+        uart.write_reg(LCR, LCR_8N1);
+        uart.write_reg(FCR, FCR_FIFO_RESET);
+        uart.write_reg(MCR, MCR_OUT2);
+        uart.write_reg(IER, IER_RX_AVAILABLE);
+        uart
+    }
+
+    fn read_reg(&self, reg: usize) -> u8 {
+        unsafe { ((self.base + reg) as *const u8).read_volatile() }
+    }
+
+    fn write_reg(&self, reg: usize, value: u8) {
+        unsafe { ((self.base + reg) as *mut u8).write_volatile(value) }
+    }
+
+    fn try_getchar(&self) -> Option<u8> {
+        (self.read_reg(LSR) & LSR_DATA_READY != 0).then(|| self.read_reg(RBR))
+    }
+}
+
+impl CharDevice for NS16550a {
+    fn read(&self) -> Option<u8> {
+        let task = current_task().unwrap();
+        loop {
+            let mut inner = self.inner.lock();
+            // a signal wakes us up without taking us out of the queue
+            inner.read_waiters.remove(&task);
+            if let Some(ch) = inner.buffer.pop_front() {
+                return Some(ch);
+            }
+            if !mark_current_blocked() {
+                return None;
+            }
+            inner.read_waiters.push(task.clone());
+            drop(inner);
+            block_current_and_run_next();
+        }
+    }
+
+    fn try_read(&self) -> Option<u8> {
+        self.inner.lock().buffer.pop_front()
+    }
+
+    fn write(&self, ch: u8) {
+        while self.read_reg(LSR) & LSR_THR_EMPTY == 0 {}
+        self.write_reg(THR, ch);
+    }
+
+    /// move everything in the receive FIFO into the buffer, then wake up the readers.
+    /// Ctrl-C isn't buffered if it interrupts the foreground task
+    fn handle_irq(&self) {
+        let mut inner = self.inner.lock();
+        while let Some(ch) = self.try_getchar() {
+            if ch == ETX && interrupt_foreground() {
+                continue;
+            }
+            if inner.buffer.len() < BUFFER_SIZE {
+                inner.buffer.push_back(ch);
+            }
+        }
+        for task in inner.read_waiters.take_all() {
+            wakeup_task(task);
+        }
+    }
+}
@@ -0,0 +1,60 @@
+mod block;
+mod chardev;
+mod plic;
+
+pub use block::BLOCK_DEVICE;
+pub use chardev::{CharDevice, UART};
+
+use lazy_static::lazy_static;
+use plic::PLIC;
+use riscv::register::sie;
+
+use crate::{board, println, task::hart_id};
+
+lazy_static! {
+    static ref IRQ_CONTROLLER: Option<PLIC> = board::plic().map(|plic| PLIC::new(plic.base));
+}
+
+/// the interrupt source of the console UART
+fn uart_irq() -> Option<usize> {
+    board::uart().and_then(|uart| uart.irq)
+}
+
+/// set up the UART and the PLIC, call it once after the kernel space is activated
+pub fn init() {
+    if let (Some(plic), Some(_), Some(irq)) = (IRQ_CONTROLLER.as_ref(), UART.as_ref(), uart_irq()) {
+        plic.set_priority(irq, 1);
+    }
+}
+
+/// route the device interrupts to the current hart, call it on every hart
+pub fn init_hart() {
+    let Some(plic) = IRQ_CONTROLLER.as_ref() else {
+        return;
+    };
+    let hart = hart_id();
+    plic.set_threshold(hart, 0);
+    if let Some(irq) = uart_irq() {
+        plic.enable(hart, irq);
+    }
+    unsafe {
+        sie::set_sext();
+    }
+}
+
+/// handle a supervisor external interrupt of the current hart
+pub fn handle_irq() {
+    let Some(plic) = IRQ_CONTROLLER.as_ref() else {
+        return;
+    };
+    let hart = hart_id();
+    // another hart may have claimed it already
+    let Some(irq) = plic.claim(hart) else {
+        return;
+    };
+    match UART.as_ref() {
+        Some(uart) if Some(irq) == uart_irq() => uart.handle_irq(),
+        _ => println!("[kernel] unexpected irq {}", irq),
+    }
+    plic.complete(hart, irq);
+}
@@ -0,0 +1,64 @@
+//! The platform-level interrupt controller, which routes the device interrupts to the harts
+
+/// Interrupt sources of a hart are configured per context, QEMU virt gives each hart
+/// an M-mode context `2 * hart` and an S-mode context `2 * hart + 1`
+pub struct PLIC {
+    base: usize,
+}
+
+impl PLIC {
+    pub fn new(base: usize) -> Self {
+        Self { base }
+    }
+
+    fn supervisor_context(hart: usize) -> usize {
+        2 * hart + 1
+    }
+
+    fn priority_ptr(&self, irq: usize) -> *mut u32 {
+        (self.base + irq * 4) as *mut u32
+    }
+
+    fn enable_ptr(&self, context: usize, irq: usize) -> *mut u32 {
+        (self.base + 0x2000 + context * 0x80 + irq / 32 * 4) as *mut u32
+    }
+
+    fn threshold_ptr(&self, context: usize) -> *mut u32 {
+        (self.base + 0x20_0000 + context * 0x1000) as *mut u32
+    }
+
+    fn claim_complete_ptr(&self, context: usize) -> *mut u32 {
+        (self.base + 0x20_0004 + context * 0x1000) as *mut u32
+    }
+
+    /// an interrupt source with priority 0 never fires
+    pub fn set_priority(&self, irq: usize, priority: u32) {
+        unsafe { self.priority_ptr(irq).write_volatile(priority) }
+    }
+
+    /// let `irq` interrupt the S mode of `hart`
+    pub fn enable(&self, hart: usize, irq: usize) {
+        let ptr = self.enable_ptr(Self::supervisor_context(hart), irq);
+        unsafe { ptr.write_volatile(ptr.read_volatile() | 1 << (irq % 32)) }
+    }
+
+    /// only the sources with a priority above `threshold` interrupt the S mode of `hart`
+    pub fn set_threshold(&self, hart: usize, threshold: u32) {
+        unsafe {
+            self.threshold_ptr(Self::supervisor_context(hart)).write_volatile(threshold)
+        }
+    }
+
+    /// take the pending interrupt with the highest priority, None if another hart has claimed it
+    pub fn claim(&self, hart: usize) -> Option<usize> {
+        let irq = unsafe { self.claim_complete_ptr(Self::supervisor_context(hart)).read_volatile() };
+        (irq != 0).then_some(irq as usize)
+    }
+
+    /// `irq` is handled, it may fire again
+    pub fn complete(&self, hart: usize, irq: usize) {
+        unsafe {
+            self.claim_complete_ptr(Self::supervisor_context(hart)).write_volatile(irq as u32)
+        }
+    }
+}
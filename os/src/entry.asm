@@ -1,13 +1,36 @@
     .section .text.entry
     .globl _start
 _start:
-    la sp, boot_stack_top
+    # a0 = hartid, keep it in tp and give every hart its own boot stack
+    mv tp, a0
+    call set_boot_stack
     call rust_main
 
+    .globl _start_secondary
+_start_secondary:
+    mv tp, a0
+    call set_boot_stack
+    call rust_main_secondary
+
+# sp = boot_stack_lower_bound + (hartid + 1) * BOOT_STACK_SIZE,
+# a hart from MAX_HARTS on has no boot stack and is parked
+set_boot_stack:
+    li t0, {max_harts}
+    bgeu tp, t0, park
+    addi t0, tp, 1
+    li t1, {boot_stack_size}
+    mul t0, t0, t1
+    la sp, boot_stack_lower_bound
+    add sp, sp, t0
+    ret
+
+park:
+    wfi
+    j park
+
     .section .bss.stack
     .globl boot_stack_lower_bound
 boot_stack_lower_bound:
-    .space 4096 * 16
+    .space {boot_stack_size} * {max_harts}
     .globl boot_stack_top
 boot_stack_top:
-    
\ No newline at end of file
@@ -0,0 +1,175 @@
+use alloc::{sync::Arc, vec::Vec};
+use bitflags::bitflags;
+use easy_fs::{block_cache_sync_all, EasyFileSystem, Inode};
+use lazy_static::lazy_static;
+
+use crate::{drivers::BLOCK_DEVICE, mm::UserBuffer, println, sync::SpinNoIrqLock, syscall::{SysError, SysResult}};
+
+use super::File;
+
+/// the longest file name a directory entry can hold
+const NAME_LENGTH_LIMIT: usize = 27;
+
+/// A file of easy-fs opened by a task, fds dup'ed from it share the offset
+pub struct OSInode {
+    readable: bool,
+    writable: bool,
+    inner: SpinNoIrqLock<OSInodeInner>,
+}
+
+struct OSInodeInner {
+    offset: usize,
+    inode: Arc<Inode>,
+}
+
+impl OSInode {
+    pub fn new(readable: bool, writable: bool, inode: Arc<Inode>) -> Self {
+        Self {
+            readable,
+            writable,
+            inner: SpinNoIrqLock::new("OS_INODE", OSInodeInner { offset: 0, inode }),
+        }
+    }
+
+    /// read from the current offset to the end of the file, used to load an ELF
+    pub fn read_all(&self) -> Vec<u8> {
+        let mut inner = self.inner.lock();
+        let mut buffer = [0u8; 512];
+        let mut v: Vec<u8> = Vec::new();
+        loop {
+            let len = inner.inode.read_at(inner.offset, &mut buffer);
+            if len == 0 {
+                break;
+            }
+            inner.offset += len;
+            v.extend_from_slice(&buffer[..len]);
+        }
+        v
+    }
+}
+
+lazy_static! {
+    /// the root directory, None without a disk. A blank disk is formatted on the first access.
+    /// It's locked so that no task is preempted while holding the locks inside easy-fs
+    static ref ROOT_INODE: Option<SpinNoIrqLock<Arc<Inode>>> = BLOCK_DEVICE.as_ref().map(|block_device| {
+        let efs = EasyFileSystem::open(block_device.clone()).unwrap_or_else(|| {
+            println!("[kernel] no file system on the disk, formatting it");
+            EasyFileSystem::create(block_device.clone(), block_device.capacity() as u32, 1)
+        });
+        SpinNoIrqLock::new("ROOT_INODE", Arc::new(EasyFileSystem::root_inode(&efs)))
+    });
+}
+
+/// list the files in the root directory, the apps are put there by easy-fs-fuse
+pub fn list_apps() {
+    println!("/**** APPS ****");
+    for app in ROOT_INODE.iter().flat_map(|root_inode| root_inode.lock().ls()) {
+        println!("{}", app);
+    }
+    println!("**************/");
+}
+
+bitflags! {
+    /// flags of open, the same values as Linux
+    #[derive(Clone, Copy)]
+    pub struct OpenFlags: u32 {
+        const RDONLY = 0;
+        const WRONLY = 1 << 0;
+        const RDWR = 1 << 1;
+        const CREAT = 1 << 6;
+        const TRUNC = 1 << 9;
+    }
+}
+
+impl OpenFlags {
+    /// (readable, writable)
+    pub fn read_write(&self) -> (bool, bool) {
+        if self.contains(Self::WRONLY) {
+            (false, true)
+        } else if self.contains(Self::RDWR) {
+            (true, true)
+        } else {
+            (true, false)
+        }
+    }
+}
+
+/// open a file in the root directory, there are no sub-directories yet. ENODEV if there is no disk
+pub fn open_file(path: &str, flags: OpenFlags) -> Result<Arc<OSInode>, SysError> {
+    let name = path.strip_prefix('/').unwrap_or(path);
+    if name.is_empty() || name.contains('/') {
+        return Err(SysError::ENOENT);
+    }
+    if name.len() > NAME_LENGTH_LIMIT {
+        return Err(SysError::ENAMETOOLONG);
+    }
+    let root_inode = ROOT_INODE.as_ref().ok_or(SysError::ENODEV)?.lock();
+    let (readable, writable) = flags.read_write();
+    let inode = match root_inode.find(name) {
+        Some(inode) => {
+            if flags.contains(OpenFlags::TRUNC) && writable {
+                inode.clear();
+            }
+            inode
+        }
+        None if flags.contains(OpenFlags::CREAT) => root_inode.create(name)?,
+        None => return Err(SysError::ENOENT),
+    };
+    Ok(Arc::new(OSInode::new(readable, writable, inode)))
+}
+
+/// easy-fs keeps the changes in its block cache, they are written back once a file opened for
+/// writing is closed
+impl Drop for OSInode {
+    fn drop(&mut self) {
+        if self.writable {
+            block_cache_sync_all();
+        }
+    }
+}
+
+/// write the changed blocks back to the disk, called before shutting down
+pub fn sync_fs() {
+    block_cache_sync_all();
+}
+
+impl File for OSInode {
+    fn readable(&self) -> bool {
+        self.readable
+    }
+
+    fn writable(&self) -> bool {
+        self.writable
+    }
+
+    fn read(&self, mut buf: UserBuffer) -> SysResult {
+        let mut inner = self.inner.lock();
+        let mut total_read_size = 0usize;
+        for slice in buf.buffers.iter_mut() {
+            let read_size = inner.inode.read_at(inner.offset, slice);
+            if read_size == 0 {
+                break;
+            }
+            inner.offset += read_size;
+            total_read_size += read_size;
+        }
+        Ok(total_read_size)
+    }
+
+    fn write(&self, buf: UserBuffer) -> SysResult {
+        let mut inner = self.inner.lock();
+        let mut total_write_size = 0usize;
+        for slice in buf.buffers.iter() {
+            match inner.inode.write_at(inner.offset, slice) {
+                Ok(write_size) => {
+                    inner.offset += write_size;
+                    total_write_size += write_size;
+                }
+                // a short write, the error comes with the next one
+                Err(_) if total_write_size > 0 => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(total_write_size)
+    }
+}
@@ -0,0 +1,23 @@
+mod inode;
+mod pipe;
+mod stdio;
+
+pub use inode::{list_apps, open_file, sync_fs, OpenFlags};
+pub use pipe::make_pipe;
+pub use stdio::{interrupt_foreground, Stdin, Stdout};
+
+use crate::{mm::UserBuffer, syscall::{SysError, SysResult}};
+
+/// Anything that can be put in a file descriptor table
+pub trait File: Send + Sync {
+    fn readable(&self) -> bool;
+    fn writable(&self) -> bool;
+    /// read into `buf`, return the number of bytes read, 0 means end of file
+    fn read(&self, buf: UserBuffer) -> SysResult;
+    /// write from `buf`, return the number of bytes written
+    fn write(&self, buf: UserBuffer) -> SysResult;
+    /// device specific control, only the console supports it
+    fn ioctl(&self, _request: usize, _arg: usize) -> SysResult {
+        Err(SysError::ENOTTY)
+    }
+}
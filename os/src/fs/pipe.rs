@@ -0,0 +1,213 @@
+use alloc::sync::{Arc, Weak};
+
+use crate::{mm::UserBuffer, sync::SpinNoIrqLock, syscall::{SysError, SysResult}, task::{block_current_and_run_next, current_task, mark_current_blocked, wakeup_task, WaitQueue}};
+
+use super::File;
+
+const RING_BUFFER_SIZE: usize = 4096;
+
+#[derive(Clone, Copy, PartialEq)]
+enum RingBufferStatus {
+    Full,
+    Empty,
+    Normal,
+}
+
+/// The buffer shared by the two ends of a pipe
+pub struct PipeRingBuffer {
+    arr: [u8; RING_BUFFER_SIZE],
+    head: usize,
+    tail: usize,
+    status: RingBufferStatus,
+    /// the ends are shared through fork and dup, they are closed when the last fd is closed
+    read_end: Weak<Pipe>,
+    write_end: Weak<Pipe>,
+    /// readers blocked on an empty buffer
+    read_waiters: WaitQueue,
+    /// writers blocked on a full buffer
+    write_waiters: WaitQueue,
+}
+
+impl PipeRingBuffer {
+    fn new() -> Self {
+        Self {
+            arr: [0; RING_BUFFER_SIZE],
+            head: 0,
+            tail: 0,
+            status: RingBufferStatus::Empty,
+            read_end: Weak::new(),
+            write_end: Weak::new(),
+            read_waiters: WaitQueue::new(),
+            write_waiters: WaitQueue::new(),
+        }
+    }
+
+    fn read_byte(&mut self) -> u8 {
+        self.status = RingBufferStatus::Normal;
+        let c = self.arr[self.head];
+        self.head = (self.head + 1) % RING_BUFFER_SIZE;
+        if self.head == self.tail {
+            self.status = RingBufferStatus::Empty;
+        }
+        c
+    }
+
+    fn write_byte(&mut self, byte: u8) {
+        self.status = RingBufferStatus::Normal;
+        self.arr[self.tail] = byte;
+        self.tail = (self.tail + 1) % RING_BUFFER_SIZE;
+        if self.tail == self.head {
+            self.status = RingBufferStatus::Full;
+        }
+    }
+
+    fn available_read(&self) -> usize {
+        if self.status == RingBufferStatus::Empty {
+            0
+        } else if self.tail > self.head {
+            self.tail - self.head
+        } else {
+            self.tail + RING_BUFFER_SIZE - self.head
+        }
+    }
+
+    fn available_write(&self) -> usize {
+        RING_BUFFER_SIZE - self.available_read()
+    }
+
+    fn all_read_ends_closed(&self) -> bool {
+        self.read_end.upgrade().is_none()
+    }
+
+    fn all_write_ends_closed(&self) -> bool {
+        self.write_end.upgrade().is_none()
+    }
+}
+
+/// Wake up all the tasks in `queue`
+fn wakeup_all(queue: &mut WaitQueue) {
+    for task in queue.take_all() {
+        wakeup_task(task);
+    }
+}
+
+/// One end of a pipe
+pub struct Pipe {
+    readable: bool,
+    writable: bool,
+    buffer: Arc<SpinNoIrqLock<PipeRingBuffer>>,
+}
+
+/// Create a pipe, return its read end and write end
+pub fn make_pipe() -> (Arc<Pipe>, Arc<Pipe>) {
+    let buffer = Arc::new(SpinNoIrqLock::new("PIPE", PipeRingBuffer::new()));
+    let read_end = Arc::new(Pipe {
+        readable: true,
+        writable: false,
+        buffer: buffer.clone(),
+    });
+    let write_end = Arc::new(Pipe {
+        readable: false,
+        writable: true,
+        buffer: buffer.clone(),
+    });
+    let mut ring = buffer.lock();
+    ring.read_end = Arc::downgrade(&read_end);
+    ring.write_end = Arc::downgrade(&write_end);
+    drop(ring);
+    (read_end, write_end)
+}
+
+impl File for Pipe {
+    fn readable(&self) -> bool {
+        self.readable
+    }
+
+    fn writable(&self) -> bool {
+        self.writable
+    }
+
+    /// block until there is something to read, return 0 once all the write ends are closed.
+    /// A signal interrupts the wait with EINTR
+    fn read(&self, buf: UserBuffer) -> SysResult {
+        assert!(self.readable);
+        let want = buf.len();
+        if want == 0 {
+            return Ok(0);
+        }
+        let mut bytes = buf.buffers.into_iter().flatten();
+        let task = current_task().unwrap();
+        loop {
+            let mut ring = self.buffer.lock();
+            // a signal wakes us up without taking us out of the queue
+            ring.read_waiters.remove(&task);
+            let available = ring.available_read();
+            if available == 0 {
+                if ring.all_write_ends_closed() {
+                    return Ok(0);
+                }
+                if !mark_current_blocked() {
+                    return Err(SysError::EINTR);
+                }
+                ring.read_waiters.push(task.clone());
+                drop(ring);
+                block_current_and_run_next();
+                continue;
+            }
+            let count = available.min(want);
+            for byte in bytes.by_ref().take(count) {
+                *byte = ring.read_byte();
+            }
+            wakeup_all(&mut ring.write_waiters);
+            return Ok(count);
+        }
+    }
+
+    /// block until everything is written, return EPIPE if all the read ends are closed.
+    /// A signal interrupts the wait, with EINTR if nothing is written yet
+    fn write(&self, buf: UserBuffer) -> SysResult {
+        assert!(self.writable);
+        let want = buf.len();
+        let mut written = 0;
+        let mut bytes = buf.buffers.into_iter().flatten();
+        let task = current_task().unwrap();
+        while written < want {
+            let mut ring = self.buffer.lock();
+            // a signal wakes us up without taking us out of the queue
+            ring.write_waiters.remove(&task);
+            if ring.all_read_ends_closed() {
+                // nobody is going to read what is left
+                return if written == 0 { Err(SysError::EPIPE) } else { Ok(written) };
+            }
+            let available = ring.available_write();
+            if available == 0 {
+                if !mark_current_blocked() {
+                    return if written == 0 { Err(SysError::EINTR) } else { Ok(written) };
+                }
+                ring.write_waiters.push(task.clone());
+                drop(ring);
+                block_current_and_run_next();
+                continue;
+            }
+            let count = available.min(want - written);
+            for byte in bytes.by_ref().take(count) {
+                ring.write_byte(*byte);
+            }
+            written += count;
+            wakeup_all(&mut ring.read_waiters);
+        }
+        Ok(written)
+    }
+}
+
+impl Drop for Pipe {
+    /// the last fd of this end is closed, wake up the other end to see EOF or EPIPE
+    fn drop(&mut self) {
+        let mut ring = self.buffer.lock();
+        if self.writable {
+            wakeup_all(&mut ring.read_waiters);
+        } else {
+            wakeup_all(&mut ring.write_waiters);
+        }
+    }
+}
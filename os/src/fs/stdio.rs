@@ -0,0 +1,153 @@
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use crate::{drivers::{CharDevice, UART}, mm::UserBuffer, sbi::{console_getchar, console_putchar}, syscall::{SysError, SysResult}, task::{current_signal_pending, pid2task, send_signal, suspend_current_and_run_next, SIGINT}};
+
+use super::File;
+
+/// Standard input, read from the console
+pub struct Stdin;
+
+/// ioctl requests of the console, they are not the termios ones of Linux.
+/// Get whether the console is in canonical mode
+pub const TCGETCANON: usize = 0x5480;
+/// Turn canonical mode on if arg is not 0, off otherwise
+pub const TCSETCANON: usize = 0x5481;
+/// Get the pid Ctrl-C sends SIGINT to, 0 if there is none
+pub const TCGETFG: usize = 0x5482;
+/// Set the pid Ctrl-C sends SIGINT to, 0 means Ctrl-C is read as a byte
+pub const TCSETFG: usize = 0x5483;
+
+/// in canonical mode a read returns at the end of a line instead of when nothing is left,
+/// it's a property of the console, so it's shared by all the tasks
+static CANONICAL: AtomicBool = AtomicBool::new(false);
+
+/// the foreground task of the console set by the shell, 0 if there is none.
+/// There are no process groups, so it's a single task
+static FOREGROUND: AtomicUsize = AtomicUsize::new(0);
+
+/// Ctrl-C is received by the console, send SIGINT to the foreground task.
+/// Return false if there is none, the byte is read as it is then
+pub fn interrupt_foreground() -> bool {
+    match FOREGROUND.load(Ordering::Relaxed) {
+        0 => false,
+        pid => pid2task(pid).map(|task| send_signal(task, SIGINT)).is_some(),
+    }
+}
+
+/// block until a byte is received, the console is polled through SBI if there is no UART.
+/// None if a signal interrupts the wait
+fn getchar() -> Option<u8> {
+    if let Some(uart) = UART.as_ref() {
+        return uart.read();
+    }
+    loop {
+        match console_getchar() {
+            0 if current_signal_pending() => return None,
+            0 => suspend_current_and_run_next(),
+            c => return Some(c as u8),
+        }
+    }
+}
+
+/// a received byte, None if there is nothing to read now
+fn try_getchar() -> Option<u8> {
+    if let Some(uart) = UART.as_ref() {
+        return uart.try_read();
+    }
+    match console_getchar() {
+        0 | usize::MAX => None,
+        c => Some(c as u8),
+    }
+}
+
+fn putchar(ch: u8) {
+    match UART.as_ref() {
+        Some(uart) => uart.write(ch),
+        None => console_putchar(ch as usize),
+    }
+}
+
+/// Standard output and standard error, written to the console
+pub struct Stdout;
+
+impl File for Stdin {
+    fn readable(&self) -> bool {
+        true
+    }
+
+    fn writable(&self) -> bool {
+        false
+    }
+
+    /// block until there is a byte, then take what is already received.
+    /// In canonical mode keep blocking until a newline, '\r' from the terminal is taken as '\n'.
+    /// A signal interrupts the wait, with EINTR if nothing is read yet
+    fn read(&self, buf: UserBuffer) -> SysResult {
+        let canonical = CANONICAL.load(Ordering::Relaxed);
+        let mut count = 0;
+        for byte in buf.buffers.into_iter().flatten() {
+            let ch = if count == 0 || canonical {
+                match getchar() {
+                    Some(ch) => ch,
+                    None if count == 0 => return Err(SysError::EINTR),
+                    None => break,
+                }
+            } else {
+                match try_getchar() {
+                    Some(ch) => ch,
+                    None => break,
+                }
+            };
+            *byte = if canonical && ch == b'\r' { b'\n' } else { ch };
+            count += 1;
+            if canonical && *byte == b'\n' {
+                break;
+            }
+        }
+        Ok(count)
+    }
+
+    fn ioctl(&self, request: usize, arg: usize) -> SysResult {
+        match request {
+            TCGETCANON => Ok(CANONICAL.load(Ordering::Relaxed) as usize),
+            TCSETCANON => {
+                CANONICAL.store(arg != 0, Ordering::Relaxed);
+                Ok(0)
+            }
+            TCGETFG => Ok(FOREGROUND.load(Ordering::Relaxed)),
+            TCSETFG => {
+                FOREGROUND.store(arg, Ordering::Relaxed);
+                Ok(0)
+            }
+            _ => Err(SysError::EINVAL),
+        }
+    }
+
+    fn write(&self, _buf: UserBuffer) -> SysResult {
+        Err(SysError::EBADF)
+    }
+}
+
+impl File for Stdout {
+    fn readable(&self) -> bool {
+        false
+    }
+
+    fn writable(&self) -> bool {
+        true
+    }
+
+    fn read(&self, _buf: UserBuffer) -> SysResult {
+        Err(SysError::EBADF)
+    }
+
+    fn write(&self, buf: UserBuffer) -> SysResult {
+        // raw bytes, a UTF-8 character may be split between two pages
+        for buffer in buf.buffers.iter() {
+            for &byte in buffer.iter() {
+                putchar(byte);
+            }
+        }
+        Ok(buf.len())
+    }
+}
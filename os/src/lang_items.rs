@@ -1,20 +1,28 @@
-use core::panic::PanicInfo;
+use core::{panic::PanicInfo, sync::atomic::{AtomicBool, Ordering}};
 
-use log::error;
+use crate::{backtrace::print_backtrace, println_unlocked, sbi::shutdown};
 
-use crate::{println, sbi::shutdown};
+/// set by the first panic, a panic while printing it shuts down at once
+static PANICKING: AtomicBool = AtomicBool::new(false);
 
+/// The message is printed whatever the log level is, and without the locks of the console
+/// and the log buffer, as the panic may come while one of them is held
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
+    if PANICKING.swap(true, Ordering::Relaxed) {
+        shutdown(true)
+    }
     if let Some(location) = info.location() {
-        error!(
-            "Panicked at {}:{} {}",
+        println_unlocked!(
+            "\x1b[31m[kernel] Panicked at {}:{} {}\x1b[0m",
             location.file(),
             location.line(),
             info.message()
         );
     } else {
-        error!("Panicked: {}", info.message());
+        println_unlocked!("\x1b[31m[kernel] Panicked: {}\x1b[0m", info.message());
     }
-    shutdown(false)
-}
\ No newline at end of file
+    print_backtrace();
+    // report the failure to QEMU, so that CI notices
+    shutdown(true)
+}
@@ -1,70 +1,124 @@
+//! Apps in the initramfs, a cpio newc archive QEMU loads with `-initrd`.
+//! The archive is found from `/chosen` of the device tree, and moved right after the kernel image
+//! at boot so that the frame allocator doesn't hand it out.
+
 use alloc::vec::Vec;
 use lazy_static::lazy_static;
 
-use crate::println;
+use crate::{board, println, sync::SpinNoIrqLock};
 
-/// Get the total number of applications.
-pub fn get_num_app() -> usize {
-    unsafe extern "C" {
-        fn _num_app();
-    }
-    unsafe {
-        (_num_app as usize as *const usize).read_volatile()
-    }
+/// magic of a cpio newc header
+const NEWC_MAGIC: &[u8] = b"070701";
+/// 6 bytes of magic and 13 fields of 8 hex digits
+const NEWC_HEADER_SIZE: usize = 110;
+/// name of the entry marking the end of the archive
+const TRAILER: &str = "TRAILER!!!";
+const S_IFMT: u32 = 0o170000;
+const S_IFREG: u32 = 0o100000;
+
+lazy_static! {
+    static ref INITRAMFS: SpinNoIrqLock<Option<&'static [u8]>> = SpinNoIrqLock::new("INITRAMFS", None);
+}
+
+/// An entry of the archive
+struct CpioEntry {
+    name: &'static str,
+    mode: u32,
+    data: &'static [u8],
+}
+
+/// Iterate over the entries of a cpio newc archive until the trailer, stop at a malformed entry
+struct CpioIter {
+    archive: &'static [u8],
+    offset: usize,
+}
+
+fn parse_hex(field: &[u8]) -> Option<u32> {
+    u32::from_str_radix(core::str::from_utf8(field).ok()?, 16).ok()
+}
+
+/// the name and the data of an entry are padded to 4 bytes
+fn align4(offset: usize) -> usize {
+    (offset + 3) & !3
 }
 
-pub fn get_app_data(app_id: usize) -> &'static [u8] {
-    unsafe extern "C" {
-        fn _num_app();
+impl Iterator for CpioIter {
+    type Item = CpioEntry;
+
+    fn next(&mut self) -> Option<CpioEntry> {
+        let header = self.archive.get(self.offset..self.offset + NEWC_HEADER_SIZE)?;
+        if &header[..6] != NEWC_MAGIC {
+            println!("[kernel] bad cpio header at {:#x} of initramfs", self.offset);
+            return None;
+        }
+        // the i-th field after the magic
+        let field = |i: usize| parse_hex(&header[6 + i * 8..6 + (i + 1) * 8]);
+        let mode = field(1)?;
+        let file_size = field(6)? as usize;
+        let name_size = field(11)? as usize;
+        let name_start = self.offset + NEWC_HEADER_SIZE;
+        // name_size counts the trailing '\0'
+        let name = self.archive.get(name_start..name_start + name_size.checked_sub(1)?)?;
+        let name = core::str::from_utf8(name).ok()?;
+        if name == TRAILER {
+            return None;
+        }
+        let data_start = align4(name_start + name_size);
+        let data = self.archive.get(data_start..data_start + file_size)?;
+        self.offset = align4(data_start + file_size);
+        Some(CpioEntry {
+            // `find .` puts "./" in front of every name
+            name: name.strip_prefix("./").unwrap_or(name),
+            mode,
+            data,
+        })
     }
-    let num_app_ptr = _num_app as usize as *const usize;
-    let num_app = get_num_app();
-    let app_start = unsafe {
-        core::slice::from_raw_parts(num_app_ptr.add(1), num_app + 1)
+}
+
+fn files() -> impl Iterator<Item = CpioEntry> {
+    let archive = INITRAMFS.lock().unwrap_or(&[]);
+    CpioIter { archive, offset: 0 }.filter(|entry| entry.mode & S_IFMT == S_IFREG)
+}
+
+/// Move the initramfs found in the device tree to `kernel_end`.
+/// Return the end of the moved archive, or `kernel_end` if there is no initramfs.
+/// It has to run before the frame allocator is initialized.
+pub fn init(kernel_end: usize) -> usize {
+    let Some((start, end)) = board::initrd() else {
+        return kernel_end;
     };
-    assert!(app_id < num_app);
+    let len = end - start;
+    // QEMU puts the archive above the kernel, copy can handle the overlap
+    assert!(start >= kernel_end, "initramfs overlaps the kernel");
     unsafe {
-        core::slice::from_raw_parts(
-            app_start[app_id] as *const u8, 
-            app_start[app_id + 1] - app_start[app_id])
+        core::ptr::copy(start as *const u8, kernel_end as *mut u8, len);
+        *INITRAMFS.lock() =
+            Some(core::slice::from_raw_parts(kernel_end as *const u8, len));
     }
+    println!("[kernel] initramfs [{:#x}, {:#x}) moved to {:#x}", start, end, kernel_end);
+    kernel_end + len
 }
 
-lazy_static!{
-    static ref APP_NAMES: Vec<&'static str> = {
-        let num_app = get_num_app();
-        unsafe extern "C" {
-            fn _app_names();
-        }
-        let mut start = _app_names as usize as *const u8;
-        let mut v = Vec::new();
-        unsafe {
-            for _ in 0..num_app {
-                let mut end = start;
-                while end.read_volatile() != b'\0' {
-                    end = end.add(1); // find out the end of str
-                }
-                let slice = core::slice::from_raw_parts(start, end as usize - start as usize);
-                let str = core::str::from_utf8(slice).unwrap();
-                v.push(str);
-                start = end.add(1);
-            }
-        }
-        v
-    };
+/// whether apps are loaded from an initramfs
+pub fn has_initramfs() -> bool {
+    INITRAMFS.lock().is_some()
 }
 
-///get app data from name
+/// get app data from name
 pub fn get_app_data_by_name(name: &str) -> Option<&'static [u8]> {
-    let num_app = get_num_app();
-    (0..num_app).find(|&i| APP_NAMES[i] == name).map(get_app_data)
+    files().find(|entry| entry.name == name).map(|entry| entry.data)
 }
 
-///list all apps
+/// names of the files in the initramfs
+pub fn app_names() -> Vec<&'static str> {
+    files().map(|entry| entry.name).collect()
+}
+
+/// list all apps
 pub fn list_apps() {
     println!("/*** APPS ***");
-    for app in APP_NAMES.iter() {
+    for app in app_names() {
         println!("{}", app);
     }
     println!("*************");
-}
\ No newline at end of file
+}
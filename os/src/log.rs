@@ -1,6 +1,73 @@
+//! The kernel logger. Records are printed to the console and kept in a ring buffer
+//! that user space reads with `sys_syslog`, each one stamped with the time since boot.
+
+use core::{
+    fmt::{self, Write},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use alloc::vec::Vec;
 use log::{Level, LevelFilter};
 
-use crate::println;
+use crate::{println, sync::SpinNoIrqLock, timer::get_time};
+
+/// size of the ring buffer of log records
+pub const LOG_BUF_SIZE: usize = 1 << 16;
+
+/// The frequency of the time CSR. It's the one of QEMU virt until `board::init` sets it,
+/// the board info can't be read before the heap is ready
+static CLOCK_FREQ: AtomicUsize = AtomicUsize::new(10_000_000);
+
+/// the newest LOG_BUF_SIZE bytes of the records, the oldest are overwritten
+struct LogBuffer {
+    buf: [u8; LOG_BUF_SIZE],
+    /// index of the oldest byte
+    start: usize,
+    len: usize,
+    /// some bytes were overwritten, the oldest record is cut then
+    wrapped: bool,
+}
+
+impl LogBuffer {
+    const fn new() -> Self {
+        Self {
+            buf: [0; LOG_BUF_SIZE],
+            start: 0,
+            len: 0,
+            wrapped: false,
+        }
+    }
+
+    fn push(&mut self, byte: u8) {
+        if self.len == LOG_BUF_SIZE {
+            self.start = (self.start + 1) % LOG_BUF_SIZE;
+            self.len -= 1;
+            self.wrapped = true;
+        }
+        self.buf[(self.start + self.len) % LOG_BUF_SIZE] = byte;
+        self.len += 1;
+    }
+
+    /// the stored bytes from the oldest, without the cut record
+    fn bytes(&self) -> impl Iterator<Item = u8> + '_ {
+        let bytes = (0..self.len).map(|i| self.buf[(self.start + i) % LOG_BUF_SIZE]);
+        let cut = if self.wrapped {
+            bytes.clone().position(|b| b == b'\n').map_or(self.len, |i| i + 1)
+        } else {
+            0
+        };
+        bytes.skip(cut)
+    }
+}
+
+impl Write for LogBuffer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        s.bytes().for_each(|byte| self.push(byte));
+        Ok(())
+    }
+}
+
+static LOG_BUFFER: SpinNoIrqLock<LogBuffer> = SpinNoIrqLock::new("LOG_BUFFER", LogBuffer::new());
 
 struct Logger;
 
@@ -10,7 +77,7 @@ pub fn init() {
     // makefile will specify the LOG var
     log::set_max_level(match option_env!("LOG") {
         Some("ERROR") => LevelFilter::Error,
-        Some("WARIN") => LevelFilter::Warn,
+        Some("WARN") => LevelFilter::Warn,
         Some("INFO") => LevelFilter::Info,
         Some("DEBUG") => LevelFilter::Debug,
         Some("TRACE") => LevelFilter::Trace,
@@ -18,6 +85,50 @@ pub fn init() {
     });
 }
 
+/// called by `board::init` once the frequency is read from the device tree
+pub fn set_clock_freq(freq: usize) {
+    CLOCK_FREQ.store(freq, Ordering::Relaxed);
+}
+
+/// the max level as a number, 0 is Off and 5 is Trace
+pub fn max_level() -> usize {
+    log::max_level() as usize
+}
+
+/// set the max level from a number, return false if it's over 5
+pub fn set_max_level(level: usize) -> bool {
+    let filter = match level {
+        0 => LevelFilter::Off,
+        1 => LevelFilter::Error,
+        2 => LevelFilter::Warn,
+        3 => LevelFilter::Info,
+        4 => LevelFilter::Debug,
+        5 => LevelFilter::Trace,
+        _ => return false,
+    };
+    log::set_max_level(filter);
+    true
+}
+
+/// the last `len` bytes of the records
+pub fn read_tail(len: usize) -> Vec<u8> {
+    let buffer = LOG_BUFFER.lock();
+    let bytes: Vec<u8> = buffer.bytes().collect();
+    bytes[bytes.len().saturating_sub(len)..].to_vec()
+}
+
+/// the number of bytes `read_tail` returns at most
+pub fn buffered_len() -> usize {
+    LOG_BUFFER.lock().bytes().count()
+}
+
+pub fn clear() {
+    let mut buffer = LOG_BUFFER.lock();
+    buffer.start = 0;
+    buffer.len = 0;
+    buffer.wrapped = false;
+}
+
 impl log::Log for Logger {
     fn enabled(&self, _metadata: &log::Metadata) -> bool {
         true
@@ -25,40 +136,30 @@ impl log::Log for Logger {
 
     fn log(&self, record: &log::Record) {
         if self.enabled(record.metadata()) {
-            match record.level() {
-                Level::Error => {
-                    println!("\x1b[{}m{} {}\x1b[0m",
-                            31,
-                            record.level().as_str(),
-                            record.args());
-                },
-                Level::Warn => {
-                    println!("\x1b[{}m{} {}\x1b[0m",
-                            93,
-                            record.level().as_str(),
-                            record.args());
-                },
-                Level::Info => {
-                    println!("\x1b[{}m{} {}\x1b[0m",
-                            34,
-                            record.level().as_str(),
-                            record.args());
-                },
-                Level::Debug => {
-                    println!("\x1b[{}m{} {}\x1b[0m",
-                            32,
-                            record.level().as_str(),
-                            record.args());
-                },
-                Level::Trace => {
-                    println!("\x1b[{}m{} {}\x1b[0m",
-                            90,
-                            record.level().as_str(),
-                            record.args());
-                },
-            }
+            let color = match record.level() {
+                Level::Error => 31,
+                Level::Warn => 93,
+                Level::Info => 34,
+                Level::Debug => 32,
+                Level::Trace => 90,
+            };
+            let freq = CLOCK_FREQ.load(Ordering::Relaxed);
+            let ticks = get_time();
+            let (sec, usec) = (ticks / freq, ticks % freq * 1_000_000 / freq);
+            println!("\x1b[{}m[{:>5}.{:06}] {} {}\x1b[0m",
+                    color,
+                    sec,
+                    usec,
+                    record.level().as_str(),
+                    record.args());
+            let _ = writeln!(LOG_BUFFER.lock(),
+                    "[{:>5}.{:06}] {} {}",
+                    sec,
+                    usec,
+                    record.level().as_str(),
+                    record.args());
         }
     }
 
     fn flush(&self) {}
-}
\ No newline at end of file
+}
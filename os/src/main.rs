@@ -3,6 +3,8 @@
 #![feature(alloc_error_handler)]
 extern crate alloc;
 
+mod backtrace;
+mod board;
 mod lang_items;
 mod sbi;
 mod console;
@@ -10,6 +12,8 @@ mod log;
 // mod batch;
 mod loader;
 mod config;
+mod drivers;
+mod fs;
 mod sync;
 mod trap;
 mod syscall;
@@ -20,16 +24,21 @@ mod mm;
 use core::arch::global_asm;
 
 use ::log::{debug, error, info, trace, warn};
+use config::{BOOT_STACK_SIZE, MAX_HARTS};
 use mm::heap_allocator::{heap_test, init_heap};
 use sbi::{console_putchar, sleep};
 
-global_asm!(include_str!("entry.asm"));
-global_asm!(include_str!("link_app.S"));
+global_asm!(
+    include_str!("entry.asm"),
+    max_harts = const MAX_HARTS,
+    boot_stack_size = const BOOT_STACK_SIZE,
+);
 
 
 // SAFETY: there is no other global function of this name
 #[unsafe(no_mangle)]
-pub fn rust_main() -> ! {
+/// `hartid` and `dtb` are passed in a0 and a1 by SBI
+pub fn rust_main(hartid: usize, dtb: usize) -> ! {
     clear_bss();
     log::init(); // init a global logger
 
@@ -44,6 +53,7 @@ pub fn rust_main() -> ! {
         fn ebss(); // end addr of BSS segment
         fn boot_stack_lower_bound(); // stack lower bound
         fn boot_stack_top(); // stack top
+        fn ekernel(); // end of the kernel image
     }
 
     info!("[kernel] .text [{:#x}, {:#x})", stext as usize, etext as usize);
@@ -64,20 +74,63 @@ pub fn rust_main() -> ! {
     // heap_test();
     // panic!("Shutdown right now!");
 
-    mm::init();
+    init_heap();
+    board::init(dtb);
+    let kernel_end = loader::init(ekernel as usize);
+    mm::init(kernel_end);
     mm::remap_test();
+    drivers::init();
     task::add_initproc();
     println!("after initproc!");
     trap::init();
     trap::enable_timer_interrupt();
+    drivers::init_hart();
     timer::set_next_trigger();
-    loader::list_apps();
+    if loader::has_initramfs() {
+        loader::list_apps();
+    } else {
+        fs::list_apps();
+    }
+    start_secondary_harts(hartid);
     task::run_tasks();
     panic!("Unreachable in rust_main!");
     // batch::init();
     // batch::run_next_app();
 }
 
+/// wake up the other harts, they share the kernel space set up by the boot hart
+fn start_secondary_harts(boot_hartid: usize) {
+    unsafe extern "C" {
+        fn _start_secondary();
+    }
+    for hartid in board::hart_ids() {
+        if hartid == boot_hartid {
+            continue;
+        }
+        if hartid >= MAX_HARTS {
+            warn!("[kernel] hart {} is beyond MAX_HARTS, left parked", hartid);
+            continue;
+        }
+        if sbi::hart_start(hartid, _start_secondary as usize, 0) != 0 {
+            warn!("[kernel] failed to start hart {}", hartid);
+        }
+    }
+}
+
+// SAFETY: there is no other global function of this name
+#[unsafe(no_mangle)]
+/// entry of the harts started by `start_secondary_harts`
+pub fn rust_main_secondary(hartid: usize) -> ! {
+    mm::KERNEL_SPACE.lock().activate();
+    trap::init();
+    trap::enable_timer_interrupt();
+    drivers::init_hart();
+    timer::set_next_trigger();
+    info!("[kernel] hart {} is up", hartid);
+    task::run_tasks();
+    panic!("Unreachable in rust_main_secondary!");
+}
+
 // need to set 0 for .bss section
 fn clear_bss() {
     unsafe extern "C" {
@@ -3,7 +3,7 @@ use core::fmt::Debug;
 use alloc::vec::Vec;
 use lazy_static::lazy_static;
 
-use crate::{config::MEMORY_END, mm::address::PhysAddr, println, sync::UPSafeCell};
+use crate::{board, mm::address::PhysAddr, println, sync::SpinNoIrqLock};
 
 use super::address::PhysPageNum;
 
@@ -93,33 +93,29 @@ impl FrameAllocator for StackFrameAllocator {
 type FrameAllocatorImpl = StackFrameAllocator;
 
 lazy_static! {
-    pub static ref FRAME_ALLOCATOR: UPSafeCell<FrameAllocatorImpl> = unsafe {
-        UPSafeCell::new(FrameAllocatorImpl::new())
-    };
+    pub static ref FRAME_ALLOCATOR: SpinNoIrqLock<FrameAllocatorImpl> =
+        SpinNoIrqLock::new("FRAME_ALLOCATOR", FrameAllocatorImpl::new());
 }
 
 
-/// initialize the frame allocator using 'ekernel' and 'MEMORY_END'
-pub fn init_frame_allocator() {
-    unsafe extern "C" {
-        fn ekernel();
-    }
-    FRAME_ALLOCATOR.exclusive_access().init(
-        PhysAddr::from(ekernel as usize).ceil(),
-        PhysAddr::from(MEMORY_END).floor()
+/// initialize the frame allocator with the memory from 'kernel_end' to the end of RAM
+pub fn init_frame_allocator(kernel_end: usize) {
+    FRAME_ALLOCATOR.lock().init(
+        PhysAddr::from(kernel_end).ceil(),
+        PhysAddr::from(board::memory_end()).floor()
     );
 }
 
 /// allocate a frame
 pub fn frame_alloc() -> Option<FrameTracker> {
     // get a ppn from global frame_allocator, and convert it into a real page content memory with FrameTracker
-    FRAME_ALLOCATOR.exclusive_access()
+    FRAME_ALLOCATOR.lock()
     .alloc().map(FrameTracker::new)
 }
 
 /// deallocate a frame, this function is automatically called by Drop from FrameTracker struct -> RAII
 fn frame_dealloc(ppn: PhysPageNum) {
-    FRAME_ALLOCATOR.exclusive_access().dealloc(ppn);
+    FRAME_ALLOCATOR.lock().dealloc(ppn);
 }
 
 /// A simple usage example of frame_allocator
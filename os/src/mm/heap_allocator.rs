@@ -1,9 +1,24 @@
+use core::alloc::{GlobalAlloc, Layout};
+
 use buddy_system_allocator::LockedHeap;
 
-use crate::{config::KERNEL_HEAP_SIZE, println};
+use crate::{config::KERNEL_HEAP_SIZE, println, sync::without_interrupts};
 
 #[global_allocator]
-static HEAP_ALLOCATOR: LockedHeap = LockedHeap::empty();
+static HEAP_ALLOCATOR: KernelHeap = KernelHeap(LockedHeap::empty());
+
+/// The interrupt handlers allocate too, so the interrupts are disabled while the heap is locked
+struct KernelHeap(LockedHeap);
+
+unsafe impl GlobalAlloc for KernelHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        without_interrupts(|| unsafe { self.0.alloc(layout) })
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        without_interrupts(|| unsafe { self.0.dealloc(ptr, layout) })
+    }
+}
 
 #[alloc_error_handler]
 /// panic when heap allocation error occurs
@@ -19,6 +34,7 @@ pub fn init_heap() {
     unsafe {
         // let heap_ptr = &raw mut HEAP_SPACE as *mut _ as *mut u8;
         HEAP_ALLOCATOR
+        .0
         .lock()
         .init(&raw mut HEAP_SPACE as usize, KERNEL_HEAP_SIZE);
     }
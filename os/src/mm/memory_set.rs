@@ -5,7 +5,7 @@ use bitflags::bitflags;
 use lazy_static::lazy_static;
 use riscv::register::satp;
 
-use crate::{config::{MEMORY_END, MMIO, PAGE_SIZE, TRAMPOLINE, TRAP_CONTEXT, USER_STACK_SIZE}, mm::address::StepByOne, println, sync::UPSafeCell};
+use crate::{board, config::{MMAP_BASE, MMAP_TOP, PAGE_SIZE, TRAMPOLINE, TRAP_CONTEXT, USER_STACK_SIZE}, mm::address::StepByOne, println, sync::SpinNoIrqLock};
 
 use super::{address::{PhysAddr, PhysPageNum, VPNRange, VirtAddr, VirtPageNum}, frame_allocator::{frame_alloc, FrameTracker}, page_table::{PTEFlgas, PageTable, PageTableEntry}};
 
@@ -23,12 +23,8 @@ unsafe extern "C" {
 }
 
 lazy_static! {
-    pub static ref KERNEL_SPACE: Arc<UPSafeCell<MemorySet>> = 
-        Arc::new(unsafe {
-            UPSafeCell::new(
-                MemorySet::new_kernel()
-            )
-        });
+    pub static ref KERNEL_SPACE: Arc<SpinNoIrqLock<MemorySet>> =
+        Arc::new(SpinNoIrqLock::new("KERNEL_SPACE", MemorySet::new_kernel()));
 }
 
 
@@ -46,15 +42,24 @@ impl MemorySet {
         }
     }
 
+    /// like `new_bare`, return None if there is no frame left for the page table
+    fn try_new_bare() -> Option<Self> {
+        Some(Self {
+            page_table: PageTable::try_new()?,
+            areas: Vec::new(),
+        })
+    }
+
     /// contains the root page (satp reg value)
     pub fn token(&self) -> usize {
         self.page_table.token()
     }
 
-    pub fn insert_framed_area(&mut self, start_va: VirtAddr, end_va: VirtAddr, permission: MapPermission) {
-        self.push(
+    /// return false if there are not enough frames for the area
+    pub fn insert_framed_area(&mut self, start_va: VirtAddr, end_va: VirtAddr, permission: MapPermission) -> bool {
+        self.try_push(
             MapArea::new(start_va, end_va, MapType::Framed, permission), 
-            None);
+            None)
     }
 
     ///Remove `MapArea` that starts with `start_vpn`
@@ -69,27 +74,36 @@ impl MemorySet {
             }
     }
 
-    fn push(&mut self, mut map_area: MapArea, data: Option<&[u8]>) {
-        map_area.map(&mut self.page_table);
+    fn push(&mut self, map_area: MapArea, data: Option<&[u8]>) {
+        assert!(self.try_push(map_area, data), "no frame left for the area");
+    }
+
+    /// like `push`, return false with nothing mapped if there are not enough frames
+    fn try_push(&mut self, mut map_area: MapArea, data: Option<&[u8]>) -> bool {
+        if !map_area.map(&mut self.page_table) {
+            return false;
+        }
         if let Some(data) = data {
             map_area.copy_data(&self.page_table, data);
         }
         self.areas.push(map_area);
+        true
     }
 
     /// Mention that trampoline is not collected by areas.
-    fn map_trampoline(&mut self) {
+    /// Return false if there is no frame left for the page tables
+    fn map_trampoline(&mut self) -> bool {
         self.page_table.map(
             VirtAddr::from(TRAMPOLINE).into(), 
             PhysAddr::from(strampoline as usize).into(), 
-            PTEFlgas::R | PTEFlgas::X);
+            PTEFlgas::R | PTEFlgas::X)
     }
 
     /// Without kernel stacks
     pub fn new_kernel() -> Self {
         let mut memory_set = Self::new_bare();
         // map trampoline
-        memory_set.map_trampoline();
+        assert!(memory_set.map_trampoline(), "no frame left for the trampoline");
         // map kernel sections
         println!(".text [{:#x}, {:#x})", stext as usize, etext as usize);
         println!(".rodata [{:#x}, {:#x})", srodata as usize, erodata as usize);
@@ -141,18 +155,18 @@ impl MemorySet {
         memory_set.push(
             MapArea::new(
                 (ekernel as usize).into(),
-                MEMORY_END.into(),
+                board::memory_end().into(),
                 MapType::Identical,
                 MapPermission::R | MapPermission::W,
             ),
             None,
         );
         println!("mapping memory-mapped registers");
-        for pair in MMIO {
+        for device in board::mmio_devices() {
             memory_set.push(
                 MapArea::new(
-                    (*pair).0.into(),
-                    ((*pair).0 + (*pair).1).into(),
+                    device.base.into(),
+                    (device.base + device.size).into(),
                     MapType::Identical,
                     MapPermission::R | MapPermission::W,
                 ),
@@ -163,22 +177,35 @@ impl MemorySet {
     }
 
     /// Include sections in elf and trampoline and TrapContext and user stack,
-    /// also returns user_sp and entry point.
-    pub fn from_elf(elf_data: &[u8]) -> (Self, usize, usize) {
-        let mut memory_set = Self::new_bare();
-        memory_set.map_trampoline();
+    /// also returns user_sp and entry point. The (empty) heap area starts at user_sp.
+    /// The segments and the user stack have to fit below the mmap window without overlapping
+    pub fn from_elf(elf_data: &[u8]) -> Result<(Self, usize, usize), ElfError> {
+        let mut memory_set = Self::try_new_bare().ok_or(ElfError::OutOfMemory)?;
+        if !memory_set.map_trampoline() {
+            return Err(ElfError::OutOfMemory);
+        }
         // map program headers of elf, with U flag
-        let elf = xmas_elf::ElfFile::new(elf_data).unwrap();
+        let elf = xmas_elf::ElfFile::new(elf_data).map_err(|_| ElfError::Malformed)?;
         let elf_header = elf.header;
         let magic = elf_header.pt1.magic;
-        assert_eq!(magic, [0x7f, 0x45, 0x4c, 0x46], "invalid elf!");
+        if magic != [0x7f, 0x45, 0x4c, 0x46] {
+            return Err(ElfError::Malformed);
+        }
         let ph_count = elf_header.pt2.ph_count(); // total program headers
         let mut max_end_vpn = VirtPageNum(0);
         for i in 0..ph_count {
-            let ph = elf.program_header(i).unwrap();
-            if ph.get_type().unwrap() == xmas_elf::program::Type::Load {
+            let ph = elf.program_header(i).map_err(|_| ElfError::Malformed)?;
+            if ph.get_type().map_err(|_| ElfError::Malformed)? == xmas_elf::program::Type::Load {
+                let end = ph.virtual_addr().checked_add(ph.mem_size());
+                let data_end = ph.offset().checked_add(ph.file_size());
+                let (Some(end), Some(data_end)) = (end, data_end) else {
+                    return Err(ElfError::Malformed);
+                };
+                if end > MMAP_BASE as u64 || ph.file_size() > ph.mem_size() || data_end > elf_data.len() as u64 {
+                    return Err(ElfError::Malformed);
+                }
                 let start_va: VirtAddr = (ph.virtual_addr() as usize).into();
-                let end_va: VirtAddr = ((ph.virtual_addr() + ph.mem_size()) as usize).into();
+                let end_va: VirtAddr = (end as usize).into();
                 let mut map_perm = MapPermission::U;
                 let ph_flags = ph.flags();
                 if ph_flags.is_read() {
@@ -191,11 +218,16 @@ impl MemorySet {
                     map_perm |= MapPermission::X;
                 }
                 let map_area = MapArea::new(start_va, end_va, MapType::Framed, map_perm);
-                max_end_vpn = map_area.vpn_range.get_end();
-                memory_set.push(
-                    map_area,
-                    Some(&elf.input[ph.offset() as usize..(ph.offset() + ph.file_size()) as usize]),
-                );
+                if memory_set.overlaps(map_area.vpn_range.get_start(), map_area.vpn_range.get_end()) {
+                    return Err(ElfError::Malformed);
+                }
+                max_end_vpn = max_end_vpn.max(map_area.vpn_range.get_end());
+                let data = &elf.input[ph.offset() as usize..data_end as usize];
+                // an empty area has no page to copy into
+                let data = (!data.is_empty()).then_some(data);
+                if !memory_set.try_push(map_area, data) {
+                    return Err(ElfError::OutOfMemory);
+                }
             }
         };
         // map user stack with U flag
@@ -204,26 +236,29 @@ impl MemorySet {
         // add with a guard page
         user_stack_bottom += PAGE_SIZE;
         let user_stack_top = user_stack_bottom + USER_STACK_SIZE;
+        if user_stack_top > MMAP_BASE {
+            return Err(ElfError::Malformed);
+        }
         memory_set.push(
             MapArea::new(
                 user_stack_bottom.into(), 
                 user_stack_top.into(), 
-                MapType::Framed, 
+                MapType::Lazy, 
             MapPermission::R | MapPermission:: W | MapPermission::U), 
             None
         );
-        // // used in sbrk
-        // memory_set.push(
-        //     MapArea::new(
-        //         user_stack_top.into(),
-        //         user_stack_top.into(),
-        //         MapType::Framed,
-        //         MapPermission::R | MapPermission::W | MapPermission::U,
-        //     ),
-        //     None,
-        // );
-        // map TrapContext
+        // used in sbrk, the heap starts empty right above user stack
         memory_set.push(
+            MapArea::new(
+                user_stack_top.into(),
+                user_stack_top.into(),
+                MapType::Lazy,
+                MapPermission::R | MapPermission::W | MapPermission::U,
+            ),
+            None,
+        );
+        // map TrapContext
+        if !memory_set.try_push(
             MapArea::new(
                 TRAP_CONTEXT.into(),
                 TRAMPOLINE.into(),
@@ -231,30 +266,115 @@ impl MemorySet {
                 MapPermission::R | MapPermission::W,
             ),
             None,
-        );
-        (
+        ) {
+            return Err(ElfError::OutOfMemory);
+        }
+        Ok((
             memory_set,
             user_stack_top,
             elf.header.pt2.entry_point() as usize,
-        )
+        ))
     }
 
-    pub fn from_existed_user(user_space: &Self) -> Self {
-        let mut memory_set = Self::new_bare();
+    /// Copy a user space for fork. Framed user pages are not copied but shared read-only
+    /// between both spaces (copy-on-write), TrapContext is still copied eagerly.
+    /// Return None if there are not enough frames for the page tables and TrapContext
+    pub fn from_existed_user(user_space: &mut Self) -> Option<Self> {
+        let mut memory_set = Self::try_new_bare()?;
         // map trampoline
-        memory_set.map_trampoline();
-        // copy data sections/trap_context/user_stack
+        if !memory_set.map_trampoline() {
+            return None;
+        }
         for area in user_space.areas.iter() {
-            let new_area = MapArea::from_another(area);
-            memory_set.push(new_area, None);
-            // copy data from another space
-            for vpn in area.vpn_range {
-                let src_ppn = user_space.translate(vpn).unwrap().ppn();
-                let dst_ppn = memory_set.translate(vpn).unwrap().ppn();
-                dst_ppn.get_bytes_array().copy_from_slice(src_ppn.get_bytes_array());
+            let mut new_area = MapArea::from_another(area);
+            if area.map_type != MapType::Identical && area.map_perm.contains(MapPermission::U) {
+                // share the frames and drop W on both sides, the first store splits the page
+                let mut pte_flags = PTEFlgas::from_bits(area.map_perm.bits()).unwrap();
+                pte_flags.remove(PTEFlgas::W);
+                for (vpn, frame) in area.data_frames.iter() {
+                    if !memory_set.page_table.map(*vpn, frame.ppn, pte_flags) {
+                        return None;
+                    }
+                    // a parent page left read-only gets W back on its next store
+                    user_space.page_table.remap(*vpn, frame.ppn, pte_flags);
+                    new_area.data_frames.insert(*vpn, frame.clone());
+                }
+                memory_set.areas.push(new_area);
+            } else {
+                if !memory_set.try_push(new_area, None) {
+                    return None;
+                }
+                // copy data from another space
+                for vpn in area.vpn_range {
+                    let src_ppn = user_space.translate(vpn).unwrap().ppn();
+                    let dst_ppn = memory_set.translate(vpn).unwrap().ppn();
+                    dst_ppn.get_bytes_array().copy_from_slice(src_ppn.get_bytes_array());
+                }
             }
         }
-        memory_set
+        Some(memory_set)
+    }
+
+    /// Try to fix a page fault at `va`, fail if it's a real access violation
+    /// or there is no frame left for the page
+    pub fn handle_page_fault(&mut self, va: VirtAddr, access: Access) -> Result<(), PageFaultError> {
+        let vpn = va.floor();
+        let Some(area) = self.areas.iter_mut().find(|area| area.contains(vpn)) else {
+            return Err(PageFaultError::AccessViolation);
+        };
+        // TrapContext is mapped without U, a user access to it is never fixed
+        if !area.map_perm.contains(user_access_perm(access)) {
+            return Err(PageFaultError::AccessViolation);
+        }
+        match self.page_table.translate(vpn).filter(|pte| pte.is_valid()) {
+            None if area.map_type == MapType::Lazy => {
+                // first touch of a demand-paged page
+                if area.map_one(&mut self.page_table, vpn) {
+                    Ok(())
+                } else {
+                    Err(PageFaultError::OutOfMemory)
+                }
+            }
+            Some(pte) => match access {
+                Access::Write => area.copy_on_write(&mut self.page_table, vpn),
+                Access::Read if pte.readable() => Ok(()),
+                Access::Execute if pte.executable() => Ok(()),
+                _ => Err(PageFaultError::AccessViolation),
+            },
+            None => Err(PageFaultError::AccessViolation),
+        }
+    }
+
+    /// Resolve faults for every page in `[start, start + len)` in advance,
+    /// since the kernel accesses user buffers by physical address and never faults itself.
+    /// The range is checked against the areas first, so nothing is allocated for a bad one,
+    /// then it stops at the first page that can't be faulted in
+    pub fn fault_in(&mut self, start: usize, len: usize, is_write: bool) -> Result<(), PageFaultError> {
+        if len == 0 {
+            return Ok(());
+        }
+        let end = start.checked_add(len).ok_or(PageFaultError::AccessViolation)?;
+        if usize::from(VirtAddr::from(start)) != start || usize::from(VirtAddr::from(end)) != end {
+            return Err(PageFaultError::AccessViolation);
+        }
+        let start_vpn = VirtAddr::from(start).floor();
+        let end_vpn = VirtAddr::from(end).ceil();
+        // every page has to be in a user area that allows the access
+        let access = if is_write { Access::Write } else { Access::Read };
+        let perm = user_access_perm(access);
+        let mut vpn = start_vpn;
+        while vpn < end_vpn {
+            let area = self
+                .areas
+                .iter()
+                .find(|area| area.contains(vpn) && area.map_perm.contains(perm))
+                .ok_or(PageFaultError::AccessViolation)?;
+            vpn = area.vpn_range.get_end();
+        }
+        for vpn in VPNRange::new(start_vpn, end_vpn) {
+            self.handle_page_fault(vpn.into(), access)?;
+        }
+        Ok(())
     }
 
     pub fn activate(&self) {
@@ -275,38 +395,221 @@ impl MemorySet {
         self.areas.clear();
     }
 
-    // pub fn shrink_to(&mut self, start: VirtAddr, new_end: VirtAddr) -> bool {
-    //     if let Some(area) = self
-    //         .areas
-    //         .iter_mut()
-    //         .find(|area| area.vpn_range.get_start() == start.floor())
-    //     {
-    //         area.shrink_to(&mut self.page_table, new_end.ceil());
-    //         true
-    //     } else {
-    //         false
-    //     }
-    // }
-
-    // pub fn append_to(&mut self, start: VirtAddr, new_end: VirtAddr) -> bool {
-    //     if let Some(area) = self
-    //         .areas
-    //         .iter_mut()
-    //         .find(|area| area.vpn_range.get_start() == start.floor())
-    //     {
-    //         area.append_to(&mut self.page_table, new_end.ceil());
-    //         true
-    //     } else {
-    //         false
-    //     }
-    // }
+    /// whether any area intersects with [start_vpn, end_vpn)
+    pub fn overlaps(&self, start_vpn: VirtPageNum, end_vpn: VirtPageNum) -> bool {
+        self.areas.iter().any(|area| {
+            area.vpn_range.get_start() < end_vpn && start_vpn < area.vpn_range.get_end()
+        })
+    }
+
+    /// shrink the area starting at `start` so that it ends at `new_end`
+    pub fn shrink_to(&mut self, start: VirtAddr, new_end: VirtAddr) -> bool {
+        if let Some(area) = self
+            .areas
+            .iter_mut()
+            .find(|area| area.vpn_range.get_start() == start.floor())
+        {
+            area.shrink_to(&mut self.page_table, new_end.ceil());
+            true
+        } else {
+            false
+        }
+    }
+
+    /// extend the area starting at `start` so that it ends at `new_end`,
+    /// fail if the new pages would run into another area or there is no frame left for them
+    pub fn append_to(&mut self, start: VirtAddr, new_end: VirtAddr) -> bool {
+        let Some(idx) = self
+            .areas
+            .iter()
+            .position(|area| area.vpn_range.get_start() == start.floor())
+        else {
+            return false;
+        };
+        let old_end = self.areas[idx].vpn_range.get_end();
+        if old_end < new_end.ceil() && self.overlaps(old_end, new_end.ceil()) {
+            return false;
+        }
+        self.areas[idx].append_to(&mut self.page_table, new_end.ceil())
+    }
+
+    /// Map an anonymous demand-paged area of `len` bytes, `start` == 0 lets the kernel choose
+    /// the address. Return the start address, or None if the range is misaligned, out of the
+    /// mmap window or overlaps an existing area.
+    pub fn mmap(&mut self, start: usize, len: usize, perm: MapPermission) -> Option<usize> {
+        let (start_vpn, end_vpn) = if start == 0 {
+            let start_vpn = self.find_free_mmap_range(len)?;
+            (start_vpn, VirtPageNum(start_vpn.0 + len.div_ceil(PAGE_SIZE)))
+        } else {
+            let range = mmap_range(start, len)?;
+            if self.overlaps(range.0, range.1) {
+                return None;
+            }
+            range
+        };
+        self.push(
+            MapArea::new(start_vpn.into(), end_vpn.into(), MapType::Lazy, perm | MapPermission::U),
+            None,
+        );
+        Some(VirtAddr::from(start_vpn).into())
+    }
+
+    /// Unmap [start, start + len), areas that are partially covered are split.
+    /// The whole range has to be mapped by mmap before.
+    pub fn munmap(&mut self, start: usize, len: usize) -> bool {
+        let Some((start_vpn, end_vpn)) = mmap_range(start, len) else {
+            return false;
+        };
+        if !self.is_covered(start_vpn, end_vpn) {
+            return false;
+        }
+        self.split_area_at(start_vpn);
+        self.split_area_at(end_vpn);
+        let page_table = &mut self.page_table;
+        self.areas.retain_mut(|area| {
+            if start_vpn <= area.vpn_range.get_start() && area.vpn_range.get_end() <= end_vpn {
+                area.unmap(page_table);
+                false
+            } else {
+                true
+            }
+        });
+        true
+    }
+
+    /// Change the permission of [start, start + len), which has to be mapped by mmap before
+    pub fn mprotect(&mut self, start: usize, len: usize, perm: MapPermission) -> bool {
+        let Some((start_vpn, end_vpn)) = mmap_range(start, len) else {
+            return false;
+        };
+        if !self.is_covered(start_vpn, end_vpn) {
+            return false;
+        }
+        self.split_area_at(start_vpn);
+        self.split_area_at(end_vpn);
+        for area in self.areas.iter_mut() {
+            if start_vpn <= area.vpn_range.get_start() && area.vpn_range.get_end() <= end_vpn {
+                area.change_perm(&mut self.page_table, perm | MapPermission::U);
+            }
+        }
+        true
+    }
+
+    /// whether every page in [start_vpn, end_vpn) belongs to some area
+    fn is_covered(&self, start_vpn: VirtPageNum, end_vpn: VirtPageNum) -> bool {
+        let mut ranges: Vec<_> = self
+            .areas
+            .iter()
+            .map(|area| (area.vpn_range.get_start(), area.vpn_range.get_end()))
+            .filter(|(l, r)| *l < end_vpn && start_vpn < *r)
+            .collect();
+        ranges.sort();
+        let mut current = start_vpn;
+        for (l, r) in ranges {
+            if l > current {
+                break;
+            }
+            current = current.max(r);
+        }
+        current >= end_vpn
+    }
+
+    /// split the area which strictly contains `vpn` into [start, vpn) and [vpn, end)
+    fn split_area_at(&mut self, vpn: VirtPageNum) {
+        if let Some(area) = self
+            .areas
+            .iter_mut()
+            .find(|area| area.vpn_range.get_start() < vpn && vpn < area.vpn_range.get_end())
+        {
+            let tail = area.split_off(vpn);
+            self.areas.push(tail);
+        }
+    }
+
+    /// first fit of `len` bytes in the mmap window
+    fn find_free_mmap_range(&self, len: usize) -> Option<VirtPageNum> {
+        if len == 0 {
+            return None;
+        }
+        let pages = len.div_ceil(PAGE_SIZE);
+        let top = VirtAddr::from(MMAP_TOP).floor();
+        let mut used: Vec<_> = self
+            .areas
+            .iter()
+            .map(|area| (area.vpn_range.get_start(), area.vpn_range.get_end()))
+            .filter(|(_, r)| *r > VirtAddr::from(MMAP_BASE).floor())
+            .collect();
+        used.sort();
+        let mut start = VirtAddr::from(MMAP_BASE).floor();
+        for (l, r) in used {
+            if start.0 + pages <= l.0 {
+                break;
+            }
+            start = start.max(r);
+        }
+        if start.0 + pages <= top.0 {
+            Some(start)
+        } else {
+            None
+        }
+    }
+
+}
+
+/// check that [start, start + len) is a non-empty page aligned range inside the mmap window
+fn mmap_range(start: usize, len: usize) -> Option<(VirtPageNum, VirtPageNum)> {
+    if start % PAGE_SIZE != 0 || len == 0 {
+        return None;
+    }
+    let end = start.checked_add(len)?;
+    if start < MMAP_BASE || end > MMAP_TOP {
+        return None;
+    }
+    Some((VirtAddr::from(start).floor(), VirtAddr::from(end).ceil()))
+}
+
+/// the kind of a user access that faulted
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    /// an instruction fetch
+    Execute,
+}
+
+/// the area permission a user access needs
+fn user_access_perm(access: Access) -> MapPermission {
+    let perm = match access {
+        Access::Read => MapPermission::R,
+        Access::Write => MapPermission::W,
+        Access::Execute => MapPermission::X,
+    };
+    perm | MapPermission::U
+}
+
+/// why `MemorySet::from_elf` couldn't load an ELF
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ElfError {
+    /// it's not an ELF, or its segments can't be laid out in user space
+    Malformed,
+    /// there are not enough frames for it
+    OutOfMemory,
+}
 
+/// why a user page fault couldn't be fixed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PageFaultError {
+    /// no area allows the access
+    AccessViolation,
+    /// there is no frame left for the page
+    OutOfMemory,
 }
 
 /// map area is a structure which controls a contiguous piece of virtual memory
 pub struct MapArea {
     vpn_range: VPNRange,
-    data_frames: BTreeMap<VirtPageNum, FrameTracker>,
+    /// frames may be shared with other spaces after fork (copy-on-write)
+    data_frames: BTreeMap<VirtPageNum, Arc<FrameTracker>>,
     map_type: MapType,
     map_perm: MapPermission,
 }
@@ -337,34 +640,90 @@ impl MapArea {
         }
     }
 
-    pub fn map_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) {
-        let ppn: PhysPageNum;
+    /// map a single page, return false if there is no frame left for it
+    pub fn map_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) -> bool {
+        let pte_flags = PTEFlgas::from_bits(self.map_perm.bits()).unwrap();
         match self.map_type {
             MapType::Identical => {
-                ppn = PhysPageNum(vpn.0); // in identical, vpn = ppn
+                // in identical, vpn = ppn
+                page_table.map(vpn, PhysPageNum(vpn.0), pte_flags)
             },
-            MapType::Framed => {
-                let frame = frame_alloc().unwrap();
-                ppn = frame.ppn; // in framed, ppn is simply random
-                self.data_frames.insert(vpn, frame);
+            MapType::Framed | MapType::Lazy => {
+                let Some(frame) = frame_alloc() else {
+                    return false;
+                };
+                // in framed, ppn is simply random
+                if !page_table.map(vpn, frame.ppn, pte_flags) {
+                    return false;
+                }
+                self.data_frames.insert(vpn, Arc::new(frame));
+                true
             }
         }
-        let pte_flags = PTEFlgas::from_bits(self.map_perm.bits()).unwrap();
-        page_table.map(vpn, ppn, pte_flags);
     }
 
     pub fn unmap_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) {
-        if self.map_type == MapType::Framed {
-            self.data_frames.remove(&vpn);
+        match self.map_type {
+            MapType::Identical => {},
+            MapType::Framed => {
+                self.data_frames.remove(&vpn);
+            },
+            MapType::Lazy => {
+                if self.data_frames.remove(&vpn).is_none() {
+                    // never touched, so never mapped
+                    return;
+                }
+            }
         }
         page_table.unmap(vpn);
     }
 
-    /// map the vpn_range (stored in its own structure) to a ppn with the pagetable
-    pub fn map(&mut self, page_table: &mut PageTable) {
+    pub fn contains(&self, vpn: VirtPageNum) -> bool {
+        self.vpn_range.get_start() <= vpn && vpn < self.vpn_range.get_end()
+    }
+
+    /// Give `vpn` a private writable frame. If the frame is still shared, copy it into a new one,
+    /// otherwise we are the last owner and simply get W back.
+    pub fn copy_on_write(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) -> Result<(), PageFaultError> {
+        let pte = match page_table.translate(vpn) {
+            Some(pte) if pte.is_valid() => pte,
+            _ => return Err(PageFaultError::AccessViolation),
+        };
+        if pte.writable() {
+            // already private
+            return Ok(());
+        }
+        let Some(frame) = self.data_frames.get(&vpn) else {
+            return Err(PageFaultError::AccessViolation);
+        };
+        let pte_flags = PTEFlgas::from_bits(self.map_perm.bits()).unwrap();
+        if Arc::strong_count(frame) == 1 {
+            page_table.remap(vpn, frame.ppn, pte_flags);
+        } else {
+            let new_frame = frame_alloc().ok_or(PageFaultError::OutOfMemory)?;
+            new_frame.ppn.get_bytes_array().copy_from_slice(frame.ppn.get_bytes_array());
+            page_table.remap(vpn, new_frame.ppn, pte_flags);
+            self.data_frames.insert(vpn, Arc::new(new_frame));
+        }
+        Ok(())
+    }
+
+    /// map the vpn_range (stored in its own structure) to a ppn with the pagetable,
+    /// return false with nothing mapped if there are not enough frames
+    pub fn map(&mut self, page_table: &mut PageTable) -> bool {
+        if self.map_type == MapType::Lazy {
+            // frames are allocated in page fault
+            return true;
+        }
         for vpn in self.vpn_range {
-            self.map_one(page_table, vpn);
+            if !self.map_one(page_table, vpn) {
+                for mapped in VPNRange::new(self.vpn_range.get_start(), vpn) {
+                    self.unmap_one(page_table, mapped);
+                }
+                return false;
+            }
         }
+        true
     }
 
     /// unmap the vpn_range (stored in its own structure) to a ppb with the pagetable
@@ -374,19 +733,54 @@ impl MapArea {
         }
     }
 
-    // pub fn shrink_to(&mut self, page_table: &mut PageTable, new_end: VirtPageNum) {
-    //     for vpn in VPNRange::new(new_end, self.vpn_range.get_end()) {
-    //         self.unmap_one(page_table, vpn);
-    //     }
-    //     self.vpn_range = VPNRange::new(self.vpn_range.get_start(), new_end);
-    // }
+    pub fn shrink_to(&mut self, page_table: &mut PageTable, new_end: VirtPageNum) {
+        for vpn in VPNRange::new(new_end, self.vpn_range.get_end()) {
+            self.unmap_one(page_table, vpn);
+        }
+        self.vpn_range = VPNRange::new(self.vpn_range.get_start(), new_end);
+    }
 
-    // pub fn append_to(&mut self, page_table: &mut PageTable, new_end: VirtPageNum) {
-    //     for vpn in VPNRange::new(self.vpn_range.get_end(), new_end) {
-    //         self.map_one(page_table, vpn);
-    //     }
-    //     self.vpn_range = VPNRange::new(self.vpn_range.get_start(), new_end);
-    // }
+    /// return false if there is no frame left for the new pages, the area is left unchanged then
+    pub fn append_to(&mut self, page_table: &mut PageTable, new_end: VirtPageNum) -> bool {
+        let old_end = self.vpn_range.get_end();
+        if self.map_type != MapType::Lazy {
+            for vpn in VPNRange::new(old_end, new_end) {
+                if !self.map_one(page_table, vpn) {
+                    for mapped in VPNRange::new(old_end, vpn) {
+                        self.unmap_one(page_table, mapped);
+                    }
+                    return false;
+                }
+            }
+        }
+        self.vpn_range = VPNRange::new(self.vpn_range.get_start(), new_end);
+        true
+    }
+
+    /// cut the area at `at`, self keeps [start, at) and the returned area takes [at, end)
+    pub fn split_off(&mut self, at: VirtPageNum) -> Self {
+        let tail = Self {
+            vpn_range: VPNRange::new(at, self.vpn_range.get_end()),
+            data_frames: self.data_frames.split_off(&at),
+            map_type: self.map_type,
+            map_perm: self.map_perm,
+        };
+        self.vpn_range = VPNRange::new(self.vpn_range.get_start(), at);
+        tail
+    }
+
+    /// change the permission of the area and every page mapped in it
+    pub fn change_perm(&mut self, page_table: &mut PageTable, map_perm: MapPermission) {
+        self.map_perm = map_perm;
+        for (vpn, frame) in self.data_frames.iter() {
+            let mut pte_flags = PTEFlgas::from_bits(map_perm.bits()).unwrap();
+            if Arc::strong_count(frame) > 1 {
+                // still shared after fork, the copy-on-write fault will grant W later
+                pte_flags.remove(PTEFlgas::W);
+            }
+            page_table.remap(*vpn, frame.ppn, pte_flags);
+        }
+    }
 
     /// data: start-aligned but maybe with shorter length
     /// assume that all frames were cleared before
@@ -415,6 +809,7 @@ impl MapArea {
 pub enum MapType {
     Identical, // exact same map used in kernel 
     Framed, // memory conversion
+    Lazy, // like Framed, but the frame is allocated on the first page fault
 }
 
 bitflags! {
@@ -430,7 +825,7 @@ bitflags! {
 
 #[allow(unused)]
 pub fn remap_test() {
-    let mut kernel_space = KERNEL_SPACE.exclusive_access();
+    let mut kernel_space = KERNEL_SPACE.lock();
     let mid_text: VirtAddr = ((stext as usize + etext as usize) / 2).into();
     let mid_rodata: VirtAddr = ((srodata as usize + erodata as usize) / 2).into();
     let mid_data: VirtAddr = ((sdata as usize + edata as usize) / 2).into();
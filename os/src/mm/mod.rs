@@ -4,15 +4,15 @@ mod page_table;
 mod frame_allocator;
 mod memory_set;
 
-pub use page_table::{translated_byte_buffer, PageTableEntry, translated_str, translate_refmut};
+pub use page_table::{copy_from_user, copy_to_user, translated_byte_buffer, translated_byte_buffer_mut, PageTableEntry, translated_str, translated_str_bounded, translate_refmut, translated_ref, BadAddress, UserBuffer};
 pub use address::{PhysAddr, PhysPageNum, VirtAddr, VirtPageNum};
 pub use frame_allocator::{frame_alloc, FrameTracker};
 pub use memory_set::remap_test;
-pub use memory_set::{MapPermission, MemorySet, KERNEL_SPACE};
+pub use memory_set::{Access, ElfError, MapPermission, MemorySet, PageFaultError, KERNEL_SPACE};
 
-/// initiate heap allocator, frame allocator and kernel space
-pub fn init() {
-    heap_allocator::init_heap();
-    frame_allocator::init_frame_allocator();
-    KERNEL_SPACE.exclusive_access().activate();
+/// initiate frame allocator and kernel space, the heap is initialized earlier to parse the device tree.
+/// The frames start from `kernel_end`, which is past the kernel image and the initramfs
+pub fn init(kernel_end: usize) {
+    frame_allocator::init_frame_allocator(kernel_end);
+    KERNEL_SPACE.lock().activate();
 }
\ No newline at end of file
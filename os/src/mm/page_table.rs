@@ -4,10 +4,13 @@ use alloc::{string::String, vec};
 use alloc::vec::Vec;
 use bitflags::{bitflags, Flags};
 
+use crate::config::PAGE_SIZE;
+
 use super::{address::{PhysPageNum, StepByOne, VirtAddr, VirtPageNum}, frame_allocator::{frame_alloc, FrameTracker}, PhysAddr};
 
 bitflags! {
     // page table entry flags
+    #[derive(Clone, Copy)]
     pub struct PTEFlgas: u8 {
         const V = 1 << 0;
         const R = 1 << 1;
@@ -66,8 +69,13 @@ pub struct PageTable {
 impl PageTable {
     /// assign a new frame for the PageTable itself
     pub fn new() -> Self {
-        let frame = frame_alloc().unwrap(); // frametracker is the unit we manage the physical page
-        PageTable { root_ppn: frame.ppn, frames: vec![frame] }        
+        Self::try_new().expect("no frame left for a page table")
+    }
+
+    /// like `new`, return None if there is no frame left
+    pub fn try_new() -> Option<Self> {
+        let frame = frame_alloc()?; // frametracker is the unit we manage the physical page
+        Some(PageTable { root_ppn: frame.ppn, frames: vec![frame] })
     }
 
     /// temporarily used to get arguments from user space, used to find the pagetable manually
@@ -78,6 +86,7 @@ impl PageTable {
         }
     }
 
+    /// return None if there is no frame left for a missing page table
     fn find_pte_create(&mut self, vpn: VirtPageNum) -> Option<&mut PageTableEntry> {
         let idxs = vpn.indexes();
         let mut ppn = self.root_ppn;
@@ -92,7 +101,7 @@ impl PageTable {
             if !pte.is_valid() {
                 // if pte itself is not valid, it means the page isn't created before
                 // we should create the frame for that page first
-                let frame = frame_alloc().unwrap();
+                let frame = frame_alloc()?;
                 *pte = PageTableEntry::new(frame.ppn, PTEFlgas::V);
                 self.frames.push(frame); // stores all frames we created in this pagetable
             }
@@ -119,12 +128,15 @@ impl PageTable {
         result
     }
 
-    /// map a vpn to a ppn
-    pub fn map(&mut self, vpn: VirtPageNum, ppn: PhysPageNum, flags: PTEFlgas) {
-        let pte = self.find_pte_create(vpn).unwrap();
+    /// map a vpn to a ppn, return false if there is no frame left for the page tables
+    pub fn map(&mut self, vpn: VirtPageNum, ppn: PhysPageNum, flags: PTEFlgas) -> bool {
+        let Some(pte) = self.find_pte_create(vpn) else {
+            return false;
+        };
         assert!(!pte.is_valid(), "vpn {:?} is mapped before mapping", vpn); // make sure that the final findings of vpn isn't used before
         // map our ppn entry to the final finding of vpn
         *pte = PageTableEntry::new(ppn, flags | PTEFlgas::V);
+        true
     }
 
     /// unmap (clear) a vpn to a ppn
@@ -135,12 +147,26 @@ impl PageTable {
         *pte = PageTableEntry::empty();
     }
 
+    /// replace the ppn and flags of a vpn that is already mapped
+    pub fn remap(&mut self, vpn: VirtPageNum, ppn: PhysPageNum, flags: PTEFlgas) {
+        let pte = self.find_pte(vpn).unwrap();
+        assert!(pte.is_valid(), "vpn {:?} is invalid before remapping", vpn);
+        *pte = PageTableEntry::new(ppn, flags | PTEFlgas::V);
+    }
+
     /// try to find a pte from the vpn, return None if it's not created instead of creating it
     /// 当遇到需要查一个特定页表（非当前正处在的地址空间的页表时），便可先通过 PageTable::from_token 新建一个页表，再调用它的 translate 方法查页表。
     pub fn translate(&self, vpn: VirtPageNum) -> Option<PageTableEntry> {
         self.find_pte(vpn).map(|pte| *pte)
     }
 
+    /// translate a user page, V, U and all of `flags` have to be set in its pte
+    pub fn translate_user(&self, vpn: VirtPageNum, flags: PTEFlgas) -> Option<PhysPageNum> {
+        self.translate(vpn)
+            .filter(|pte| pte.is_valid() && pte.flags().contains(flags | PTEFlgas::U))
+            .map(|pte| pte.ppn())
+    }
+
     pub fn translate_va(&self, va: VirtAddr) -> Option<PhysAddr> {
         self.find_pte(va.clone().floor()).map(|pte| {
             let aligned_pa: PhysAddr = pte.ppn().into();
@@ -155,17 +181,34 @@ impl PageTable {
     }
 }
 
-/// transfer the virt addr (ptr..ptr+len) to the physical addr
-/// return a Vec< &mut [u8] >
-pub fn translated_byte_buffer(token: usize, ptr: *const u8, len: usize) -> Vec<&'static mut [u8]> {
+/// A user pointer the kernel refused to access, carrying the offending virtual address
+#[derive(Clone, Copy, Debug)]
+pub struct BadAddress(pub usize);
+
+/// make sure [ptr, ptr + len) doesn't wrap around and stays in canonical Sv39 addresses
+fn user_range(ptr: usize, len: usize) -> Result<(usize, usize), BadAddress> {
+    let end = ptr.checked_add(len).ok_or(BadAddress(ptr))?;
+    if usize::from(VirtAddr::from(ptr)) != ptr || usize::from(VirtAddr::from(end)) != end {
+        return Err(BadAddress(ptr));
+    }
+    Ok((ptr, end))
+}
+
+/// transfer the virt addr (ptr..ptr+len) to the physical addr,
+/// every page must be valid and accessible from U-mode with `flags`
+fn translated_user_buffer(
+    token: usize,
+    ptr: usize,
+    len: usize,
+    flags: PTEFlgas,
+) -> Result<Vec<&'static mut [u8]>, BadAddress> {
     let page_table = PageTable::from_token(token); // token is the value of satp, which contains the pointer of root page
-    let mut start = ptr as usize;
-    let end = start + len;
+    let (mut start, end) = user_range(ptr, len)?;
     let mut v = Vec::new();
     while start < end {
         let start_va = VirtAddr::from(start);
         let mut vpn = start_va.floor(); // calculate the vpn from start_va
-        let ppn = page_table.translate(vpn).unwrap().ppn();
+        let ppn = page_table.translate_user(vpn, flags).ok_or(BadAddress(start))?;
         vpn.step(); // get into the next page
         let mut end_va: VirtAddr = vpn.into();
         end_va = end_va.min(VirtAddr::from(end)); // make sure the range doesn't cover the next page: within one page
@@ -179,32 +222,113 @@ pub fn translated_byte_buffer(token: usize, ptr: *const u8, len: usize) -> Vec<&
         }
         start = end_va.into(); // go to next page
     }
-    v
+    Ok(v)
+}
+
+/// Translate a user buffer the kernel is going to read from, return a Vec< &mut [u8] >
+pub fn translated_byte_buffer(token: usize, ptr: *const u8, len: usize) -> Result<Vec<&'static mut [u8]>, BadAddress> {
+    translated_user_buffer(token, ptr as usize, len, PTEFlgas::R)
+}
+
+/// Translate a user buffer the kernel is going to write into, return a Vec< &mut [u8] >
+pub fn translated_byte_buffer_mut(token: usize, ptr: *mut u8, len: usize) -> Result<Vec<&'static mut [u8]>, BadAddress> {
+    translated_user_buffer(token, ptr as usize, len, PTEFlgas::W)
+}
+
+/// Copy `value` into user space, unlike `translate_refmut` it may cross a page boundary
+pub fn copy_to_user<T>(token: usize, ptr: *mut T, value: &T) -> Result<(), BadAddress> {
+    let bytes = unsafe {
+        core::slice::from_raw_parts(value as *const T as *const u8, core::mem::size_of::<T>())
+    };
+    let mut offset = 0;
+    for buffer in translated_byte_buffer_mut(token, ptr as *mut u8, bytes.len())? {
+        buffer.copy_from_slice(&bytes[offset..offset + buffer.len()]);
+        offset += buffer.len();
+    }
+    Ok(())
+}
+
+/// Copy a value out of user space, it may cross a page boundary.
+/// `T` must be valid for any bytes, e.g. integers and structs of them
+pub fn copy_from_user<T: Copy>(token: usize, ptr: *const T) -> Result<T, BadAddress> {
+    let mut value = core::mem::MaybeUninit::<T>::uninit();
+    let bytes = unsafe {
+        core::slice::from_raw_parts_mut(value.as_mut_ptr() as *mut u8, core::mem::size_of::<T>())
+    };
+    let mut offset = 0;
+    for buffer in translated_byte_buffer(token, ptr as *const u8, bytes.len())? {
+        bytes[offset..offset + buffer.len()].copy_from_slice(buffer);
+        offset += buffer.len();
+    }
+    Ok(unsafe { value.assume_init() })
+}
+
+/// A user buffer which may span several pages, translated by `translated_byte_buffer(_mut)`
+pub struct UserBuffer {
+    pub buffers: Vec<&'static mut [u8]>,
+}
+
+impl UserBuffer {
+    pub fn new(buffers: Vec<&'static mut [u8]>) -> Self {
+        Self { buffers }
+    }
+
+    /// total length in bytes
+    pub fn len(&self) -> usize {
+        self.buffers.iter().map(|b| b.len()).sum()
+    }
 }
 
 /// Translate a pointer to a mutable u8 Vec end with `\0` through page table to a `String`
-pub fn translated_str(token: usize, ptr: *const u8) -> String {
+pub fn translated_str(token: usize, ptr: *const u8) -> Result<String, BadAddress> {
+    translated_str_bounded(token, ptr, usize::MAX).map(|string| string.unwrap())
+}
+
+/// Like `translated_str`, but stop copying and return None once the string is longer than `max_len`
+pub fn translated_str_bounded(token: usize, ptr: *const u8, max_len: usize) -> Result<Option<String>, BadAddress> {
     let page_table = PageTable::from_token(token);
     let mut string = String::new();
     let mut va = ptr as usize;
     loop {
-        let ch: u8 = *(page_table
-            .translate_va(VirtAddr::from(va))
-            .unwrap()
-            .get_mut());
+        user_range(va, 1)?;
+        let ppn = page_table
+            .translate_user(VirtAddr::from(va).floor(), PTEFlgas::R)
+            .ok_or(BadAddress(va))?;
+        let ch = ppn.get_bytes_array()[VirtAddr::from(va).page_offset()];
         if ch == 0 {
             break;
-        } else {
-            string.push(ch as char);
-            va += 1;
         }
+        string.push(ch as char);
+        if string.len() > max_len {
+            return Ok(None);
+        }
+        va += 1;
     }
-    string
+    Ok(Some(string))
 }
 
-/// Translate a generic through page table and return a mutable reference
-pub fn translate_refmut<T>(token: usize, ptr: *mut T) -> &'static mut T {
+/// Translate a user object of type T, it has to be aligned and must not cross a page,
+/// otherwise it's not contiguous in physical memory
+fn translated_user_object<T>(token: usize, va: usize, flags: PTEFlgas) -> Result<PhysAddr, BadAddress> {
     let page_table = PageTable::from_token(token);
-    let va = ptr as usize;
-    page_table.translate_va(VirtAddr::from(va)).unwrap().get_mut()
-}
\ No newline at end of file
+    user_range(va, core::mem::size_of::<T>())?;
+    let user_va = VirtAddr::from(va);
+    if va % core::mem::align_of::<T>() != 0
+        || user_va.page_offset() + core::mem::size_of::<T>() > PAGE_SIZE
+    {
+        return Err(BadAddress(va));
+    }
+    let ppn = page_table.translate_user(user_va.floor(), flags).ok_or(BadAddress(va))?;
+    let aligned_pa: PhysAddr = ppn.into();
+    Ok(PhysAddr::from(aligned_pa.0 + user_va.page_offset()))
+}
+
+/// Translate a generic through page table and return a reference
+pub fn translated_ref<T>(token: usize, ptr: *const T) -> Result<&'static T, BadAddress> {
+    translated_user_object::<T>(token, ptr as usize, PTEFlgas::R).map(|pa| pa.get_ref())
+}
+
+/// Translate a generic through page table and return a mutable reference
+pub fn translate_refmut<T>(token: usize, ptr: *mut T) -> Result<&'static mut T, BadAddress> {
+    translated_user_object::<T>(token, ptr as usize, PTEFlgas::W).map(|pa| pa.get_mut())
+}
@@ -2,7 +2,7 @@ use core::arch::asm;
 
 use riscv::register::{sie::set_stimer, time};
 
-use crate::println;
+use crate::{board::clock_freq, println};
 
 // legacy extensions: ignore fid
 const SBI_CONSOLE_PUTCHAR: usize = 1;
@@ -16,10 +16,11 @@ const SBI_REMOTE_SFENCE_VMA_ASID: usize = 7;
 const SRST_EXTENSION: usize = 0x53525354;
 const SYSTEM_RESET_FUNCTION: usize = 0;
 
-// for sleep system call
-const CLOCK_FREQ: usize = 10_000_000; // 10 MHz
 const SBI_SET_TIMER: usize = 0x54494D45;
 
+const HSM_EXTENSION: usize = 0x48534D;
+const HART_START_FUNCTION: usize = 0;
+
 #[inline(always)]
 fn sbi_call(eid: usize, fid: usize, arg0: usize, arg1: usize, arg2: usize) -> usize {
     let mut ret;
@@ -48,7 +49,7 @@ pub fn console_getchar() -> usize {
 
 pub fn sleep(t: usize) {
     let current_time = time::read(); // get the cur time
-    let wake_up_time = current_time + t * CLOCK_FREQ;
+    let wake_up_time = current_time + t * clock_freq();
     // SAFETY: allow the timer interrupt by riscv lib
     unsafe { set_stimer(); }
     sbi_call(SBI_SET_TIMER, 0, wake_up_time, 0, 0); // set the timer
@@ -61,6 +62,18 @@ pub fn set_timer(timer: usize) {
     sbi_rt::set_timer(timer as _);
 }
 
+/// start `hartid` at the physical address `start_addr` in S mode with a0 = hartid, a1 = `opaque`,
+/// return the SBI error code, 0 on success
+pub fn hart_start(hartid: usize, start_addr: usize, opaque: usize) -> usize {
+    sbi_call(HSM_EXTENSION, HART_START_FUNCTION, hartid, start_addr, opaque)
+}
+
+/// flush the TLB entries of [start, start + size) on the harts in `hart_mask`
+pub fn remote_sfence_vma(hart_mask: usize, start: usize, size: usize) {
+    // the legacy call takes the address of the mask
+    sbi_call(SBI_REMOTE_SFENCE_VMA, 0, &hart_mask as *const usize as usize, start, size);
+}
+
 pub fn shutdown(failure: bool) -> !{
     if !failure {
         // shutdown with no reason
@@ -0,0 +1,142 @@
+use core::{
+    cell::UnsafeCell,
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+use riscv::register::sstatus;
+
+#[cfg(debug_assertions)]
+use crate::println;
+use crate::{config::MAX_HARTS, task::hart_id};
+
+/// no hart holds the lock
+const NO_OWNER: usize = usize::MAX;
+/// spins before a waiter reports a possible deadlock, only checked in debug builds
+#[cfg(debug_assertions)]
+const DEADLOCK_SPINS: usize = 1 << 26;
+
+/// how many `SpinNoIrqLock`s each hart holds
+static LOCK_DEPTH: [AtomicUsize; MAX_HARTS] = [const { AtomicUsize::new(0) }; MAX_HARTS];
+/// whether the interrupts of each hart were enabled before its first lock
+static SAVED_SIE: [AtomicBool; MAX_HARTS] = [const { AtomicBool::new(false) }; MAX_HARTS];
+
+/// disable the interrupts of the current hart, they are enabled again by the last `pop_off`,
+/// so the guards may be dropped in any order
+fn push_off() {
+    let sie = sstatus::read().sie();
+    unsafe {
+        sstatus::clear_sie();
+    }
+    let hart = hart_id();
+    if LOCK_DEPTH[hart].fetch_add(1, Ordering::Relaxed) == 0 {
+        SAVED_SIE[hart].store(sie, Ordering::Relaxed);
+    }
+}
+
+fn pop_off() {
+    let hart = hart_id();
+    if LOCK_DEPTH[hart].fetch_sub(1, Ordering::Relaxed) == 1 && SAVED_SIE[hart].load(Ordering::Relaxed) {
+        unsafe {
+            sstatus::set_sie();
+        }
+    }
+}
+
+/// run `f` with the interrupts of the current hart disabled, for the locks that aren't ours
+pub fn without_interrupts<R>(f: impl FnOnce() -> R) -> R {
+    push_off();
+    let ret = f();
+    pop_off();
+    ret
+}
+
+/// A spin lock which disables the interrupts of the hart while it is held,
+/// so an interrupt handler on the same hart can't spin on it forever
+pub struct SpinNoIrqLock<T: ?Sized> {
+    /// shown when a deadlock or recursive locking is found
+    #[cfg_attr(not(debug_assertions), allow(unused))]
+    name: &'static str,
+    locked: AtomicBool,
+    /// the hart holding the lock
+    owner: AtomicUsize,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send> Sync for SpinNoIrqLock<T> {}
+unsafe impl<T: ?Sized + Send> Send for SpinNoIrqLock<T> {}
+
+pub struct SpinNoIrqLockGuard<'a, T: ?Sized> {
+    lock: &'a SpinNoIrqLock<T>,
+}
+
+impl<T> SpinNoIrqLock<T> {
+    pub const fn new(name: &'static str, data: T) -> Self {
+        Self {
+            name,
+            locked: AtomicBool::new(false),
+            owner: AtomicUsize::new(NO_OWNER),
+            data: UnsafeCell::new(data),
+        }
+    }
+}
+
+impl<T: ?Sized> SpinNoIrqLock<T> {
+    /// spin until the lock is acquired, the interrupts stay disabled until the guard is dropped
+    pub fn lock(&self) -> SpinNoIrqLockGuard<'_, T> {
+        push_off();
+        let hart = hart_id();
+        #[cfg(debug_assertions)]
+        if self.owner.load(Ordering::Relaxed) == hart {
+            panic!("[kernel] recursive locking of {} on hart {}", self.name, hart);
+        }
+        #[cfg(debug_assertions)]
+        let mut spins = 0usize;
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            while self.locked.load(Ordering::Relaxed) {
+                core::hint::spin_loop();
+                #[cfg(debug_assertions)]
+                {
+                    spins += 1;
+                    if spins == DEADLOCK_SPINS {
+                        println!(
+                            "[kernel] possible deadlock: hart {} waits for {} held by hart {}",
+                            hart,
+                            self.name,
+                            self.owner.load(Ordering::Relaxed),
+                        );
+                    }
+                }
+            }
+        }
+        self.owner.store(hart, Ordering::Relaxed);
+        SpinNoIrqLockGuard { lock: self }
+    }
+}
+
+impl<T: ?Sized> Deref for SpinNoIrqLockGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for SpinNoIrqLockGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for SpinNoIrqLockGuard<'_, T> {
+    /// release the lock, the interrupts are enabled again after the last lock of the hart
+    fn drop(&mut self) {
+        self.lock.owner.store(NO_OWNER, Ordering::Relaxed);
+        self.lock.locked.store(false, Ordering::Release);
+        pop_off();
+    }
+}
@@ -1,3 +1,3 @@
-mod up;
+mod lock;
 
-pub use up::UPSafeCell;
\ No newline at end of file
+pub use lock::{without_interrupts, SpinNoIrqLock, SpinNoIrqLockGuard};
@@ -0,0 +1,96 @@
+use easy_fs::FsError;
+
+use crate::mm::{BadAddress, ElfError, PageFaultError};
+
+/// Error numbers of syscalls, compatible with Linux.
+/// `syscall` returns them to user space as negative values.
+#[allow(unused)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(isize)]
+pub enum SysError {
+    /// Operation not permitted
+    EPERM = 1,
+    /// No such file or directory
+    ENOENT = 2,
+    /// No such process
+    ESRCH = 3,
+    /// Interrupted system call
+    EINTR = 4,
+    /// I/O error
+    EIO = 5,
+    /// Argument list too long
+    E2BIG = 7,
+    /// Exec format error
+    ENOEXEC = 8,
+    /// Bad file number
+    EBADF = 9,
+    /// No child processes
+    ECHILD = 10,
+    /// Try again
+    EAGAIN = 11,
+    /// Out of memory
+    ENOMEM = 12,
+    /// Bad address
+    EFAULT = 14,
+    /// File exists
+    EEXIST = 17,
+    /// No such device
+    ENODEV = 19,
+    /// Not a directory
+    ENOTDIR = 20,
+    /// Is a directory
+    EISDIR = 21,
+    /// Invalid argument
+    EINVAL = 22,
+    /// Too many open files
+    EMFILE = 24,
+    /// Not a typewriter
+    ENOTTY = 25,
+    /// File too large
+    EFBIG = 27,
+    /// No space left on device
+    ENOSPC = 28,
+    /// Broken pipe
+    EPIPE = 32,
+    /// File name too long
+    ENAMETOOLONG = 36,
+    /// Function not implemented
+    ENOSYS = 38,
+}
+
+/// Result of a syscall handler, Ok holds the (non-negative) return value
+pub type SysResult = Result<usize, SysError>;
+
+impl From<BadAddress> for SysError {
+    fn from(_: BadAddress) -> Self {
+        SysError::EFAULT
+    }
+}
+
+impl From<PageFaultError> for SysError {
+    fn from(err: PageFaultError) -> Self {
+        match err {
+            PageFaultError::AccessViolation => SysError::EFAULT,
+            PageFaultError::OutOfMemory => SysError::ENOMEM,
+        }
+    }
+}
+
+impl From<ElfError> for SysError {
+    fn from(err: ElfError) -> Self {
+        match err {
+            ElfError::Malformed => SysError::ENOEXEC,
+            ElfError::OutOfMemory => SysError::ENOMEM,
+        }
+    }
+}
+
+impl From<FsError> for SysError {
+    fn from(err: FsError) -> Self {
+        match err {
+            FsError::Exists => SysError::EEXIST,
+            FsError::NoSpace => SysError::ENOSPC,
+            FsError::FileTooLarge => SysError::EFBIG,
+        }
+    }
+}
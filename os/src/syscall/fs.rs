@@ -1,72 +1,125 @@
-use core::panic;
+use super::{SysError, SysResult};
+use crate::{config::MAX_FD, fs::{make_pipe, open_file, OpenFlags}, mm::{translate_refmut, translated_byte_buffer, translated_byte_buffer_mut, translated_str, UserBuffer}, task::{current_task, current_user_token}};
 
-use log::{debug, info};
+pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> SysResult {
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    let file = inner.get_file(fd).ok_or(SysError::EBADF)?;
+    if !file.writable() {
+        return Err(SysError::EBADF);
+    }
+    inner.memory_set.fault_in(buf as usize, len, false)?;
+    let token = inner.get_user_token();
+    // release the task before writing, the file may block
+    drop(inner);
+    file.write(UserBuffer::new(translated_byte_buffer(token, buf, len)?))
+}
+
+pub fn sys_read(fd: usize, buf: *mut u8, len: usize) -> SysResult {
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    let file = inner.get_file(fd).ok_or(SysError::EBADF)?;
+    if !file.readable() {
+        return Err(SysError::EBADF);
+    }
+    // the buffer may still be shared with parent after fork
+    inner.memory_set.fault_in(buf as usize, len, true)?;
+    let token = inner.get_user_token();
+    drop(inner);
+    // check the buffer before consuming any input
+    file.read(UserBuffer::new(translated_byte_buffer_mut(token, buf, len)?))
+}
+
+/// device specific control of `fd`, return ENOTTY if it isn't the console
+pub fn sys_ioctl(fd: usize, request: usize, arg: usize) -> SysResult {
+    let file = current_task()
+        .unwrap()
+        .inner_exclusive_access()
+        .get_file(fd)
+        .ok_or(SysError::EBADF)?;
+    file.ioctl(request, arg)
+}
 
-use crate::{mm::translated_byte_buffer, print, sbi::console_getchar, syscall::process::sys_exit, task::{current_user_token, suspend_current_and_run_next}};
+/// open a file of easy-fs. There is only the root directory, so `dirfd` is ignored
+/// and every path is looked up in it. The mode of a created file is ignored as well.
+pub fn sys_openat(_dirfd: isize, path: *const u8, flags: u32) -> SysResult {
+    let path = translated_str(current_user_token(), path)?;
+    let flags = OpenFlags::from_bits(flags).ok_or(SysError::EINVAL)?;
+    let file = open_file(path.as_str(), flags)?;
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    let fd = inner.alloc_fd().ok_or(SysError::EMFILE)?;
+    inner.fd_table[fd] = Some(file);
+    Ok(fd)
+}
 
-const FD_STDIN: usize = 0;
-const FD_STDOUT: usize = 1; // to the terminal
+pub fn sys_close(fd: usize) -> SysResult {
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    let file = inner
+        .fd_table
+        .get_mut(fd)
+        .and_then(Option::take)
+        .ok_or(SysError::EBADF)?;
+    // the file itself is closed when its last reference is dropped, closing a pipe end
+    // wakes up the other end, which locks the tasks waiting there
+    drop(inner);
+    drop(file);
+    Ok(0)
+}
 
-pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> isize {
-    // // for experiment 2
-    // let app_range = get_current_app_addr();
-    // let stack_range = get_user_stack_range();
-    // let buf_begin_pointer = buf as usize;
-    // let buf_end_pointer = unsafe{buf.offset(len as isize)} as usize;
-    // if !(
-    //         (buf_begin_pointer >= app_range[0] && buf_begin_pointer < app_range[1]) && 
-    //         (buf_end_pointer >= app_range[0] && buf_end_pointer < app_range[1])
-    //     )&&
-    //     !(
-    //         (buf_begin_pointer >= stack_range[0] && buf_begin_pointer < stack_range[1]) && 
-    //         (buf_end_pointer >= stack_range[0] && buf_end_pointer < stack_range[1])
-    //     ) {
-    //     return -1 as isize;
-    // }
-    match fd {
-        FD_STDOUT => {
-            // let slice = unsafe {
-            //     core::slice::from_raw_parts(buf, len)
-            // };
-            // let str = core::str::from_utf8(slice).unwrap();
-            // print!("{}", str);
-            // len as isize
-            let buffers = translated_byte_buffer(current_user_token(), buf, len);
-            for buffer in buffers {
-                print!("{}", core::str::from_utf8(buffer).unwrap());
-            }
-            len as isize
-        },
-        _ => {
-            return -1 as isize;
-            // panic!("Unsupported fd in sys_write!");
-        }
+/// create a pipe, store its read fd and write fd in `pipe`, no flag is supported
+pub fn sys_pipe2(pipe: *mut [i32; 2], flags: usize) -> SysResult {
+    if flags != 0 {
+        return Err(SysError::EINVAL);
     }
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    inner.memory_set.fault_in(pipe as usize, core::mem::size_of::<[i32; 2]>(), true)?;
+    let fds = translate_refmut(inner.get_user_token(), pipe)?;
+    let read_fd = inner.alloc_fd().ok_or(SysError::EMFILE)?;
+    let (pipe_read, pipe_write) = make_pipe();
+    inner.fd_table[read_fd] = Some(pipe_read);
+    let Some(write_fd) = inner.alloc_fd() else {
+        // the read end is dropped after releasing the lock, as in `sys_close`
+        let pipe_read = inner.fd_table[read_fd].take();
+        drop(inner);
+        drop(pipe_read);
+        return Err(SysError::EMFILE);
+    };
+    inner.fd_table[write_fd] = Some(pipe_write);
+    *fds = [read_fd as i32, write_fd as i32];
+    Ok(0)
+}
+
+/// duplicate `fd` to the lowest free fd
+pub fn sys_dup(fd: usize) -> SysResult {
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    let file = inner.get_file(fd).ok_or(SysError::EBADF)?;
+    let new_fd = inner.alloc_fd().ok_or(SysError::EMFILE)?;
+    inner.fd_table[new_fd] = Some(file);
+    Ok(new_fd)
 }
 
-pub fn sys_read(fd: usize, buf: *const u8, len: usize) -> isize {
-    match fd {
-        FD_STDIN => {
-            assert_eq!(len, 1, "Only support len = 1 in sys_read");
-            let mut c: usize;
-            loop {
-                c = console_getchar();
-                if c == 0 {
-                    suspend_current_and_run_next();
-                    continue;
-                } else {
-                    break;
-                }
-            }
-            let ch = c as u8;
-            let mut buffers = translated_byte_buffer(current_user_token(), buf, len);
-            unsafe {
-                buffers[0].as_mut_ptr().write_volatile(ch);
-            }
-            1
-        },
-        _ => {
-            panic!("Unsupported fd in sys_read");
-        }
+/// duplicate `old_fd` to `new_fd`, closing `new_fd` first if it's open.
+/// No flag is supported, and unlike dup2 `old_fd` == `new_fd` is EINVAL.
+pub fn sys_dup3(old_fd: usize, new_fd: usize, flags: usize) -> SysResult {
+    if flags != 0 || old_fd == new_fd {
+        return Err(SysError::EINVAL);
     }
-}
\ No newline at end of file
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    let file = inner.get_file(old_fd).ok_or(SysError::EBADF)?;
+    if new_fd >= MAX_FD {
+        return Err(SysError::EBADF);
+    }
+    if new_fd >= inner.fd_table.len() {
+        inner.fd_table.resize(new_fd + 1, None);
+    }
+    let old_file = inner.fd_table[new_fd].replace(file);
+    // closing a pipe end wakes up the other end, which locks the tasks waiting there
+    drop(inner);
+    drop(old_file);
+    Ok(new_fd)
+}
@@ -1,30 +1,108 @@
-use fs::{sys_read, sys_write};
-use process::{sys_exec, sys_exit, sys_fork, sys_get_time, sys_getpid, sys_waitpid, sys_yield};
+pub use errno::{SysError, SysResult};
+use fs::{sys_close, sys_dup, sys_dup3, sys_ioctl, sys_openat, sys_pipe2, sys_read, sys_write};
+use log::warn;
+use process::{
+    sys_exec, sys_exit, sys_fork, sys_get_time, sys_getpid, sys_mmap, sys_mprotect, sys_munmap,
+    sys_nanosleep, sys_sbrk, sys_set_priority, sys_trace, sys_waitpid, sys_yield,
+};
+use signal::{sys_kill, sys_sigaction, sys_sigprocmask, sys_sigreturn};
+use syslog::sys_syslog;
 
+use crate::{task::{current_task, SignalAction}, timer::{get_time, TimeSpec}};
+
+mod errno;
 mod fs;
 mod process;
+mod signal;
+mod syslog;
+mod trace;
 
+const SYSCALL_DUP: usize = 23;
+const SYSCALL_DUP3: usize = 24;
+const SYSCALL_IOCTL: usize = 29;
+const SYSCALL_OPENAT: usize = 56;
+const SYSCALL_CLOSE: usize = 57;
+const SYSCALL_PIPE2: usize = 59;
 const SYSCALL_READ: usize = 63;
 const SYSCALL_WRITE: usize = 64;
 const SYSCALL_EXIT: usize = 93;
+const SYSCALL_NANOSLEEP: usize = 101;
+const SYSCALL_SYSLOG: usize = 116;
 const SYSCALL_YIELD: usize = 124;
+const SYSCALL_KILL: usize = 129;
+const SYSCALL_SIGACTION: usize = 134;
+const SYSCALL_SIGPROCMASK: usize = 135;
+const SYSCALL_SIGRETURN: usize = 139;
+const SYSCALL_SET_PRIORITY: usize = 140;
 const SYSCALL_GET_TIME: usize = 169;
 const SYSCALL_GETPID: usize = 172;
+const SYSCALL_SBRK: usize = 214;
+const SYSCALL_MUNMAP: usize = 215;
 const SYSCALL_FORK: usize = 220;
 const SYSCALL_EXEC: usize = 221;
+const SYSCALL_MMAP: usize = 222;
+const SYSCALL_MPROTECT: usize = 226;
 const SYSCALL_WAITPID: usize = 260;
+/// not in Linux, turn on the strace-like logging of the current task
+const SYSCALL_TRACE: usize = 1000;
 
+/// dispatch a syscall, errors are returned to user space as negative errno.
+/// The calls of a traced task are logged with their results
 pub fn syscall(syscall_id: usize, args: [usize; 3]) -> isize {
+    let task = current_task().unwrap();
+    let traced = task.inner_exclusive_access().trace;
+    let pid = task.getpid();
+    drop(task);
+    let result = if traced {
+        let call = trace::format_call(syscall_id, args);
+        if syscall_id == SYSCALL_EXIT {
+            trace::log_noreturn(pid, &call);
+        }
+        let start = get_time();
+        let result = dispatch(syscall_id, args);
+        trace::log_return(pid, &call, &result, get_time() - start);
+        result
+    } else {
+        dispatch(syscall_id, args)
+    };
+    match result {
+        Ok(ret) => ret as isize,
+        Err(errno) => -(errno as isize),
+    }
+}
+
+fn dispatch(syscall_id: usize, args: [usize; 3]) -> SysResult {
     match syscall_id {
-        SYSCALL_READ => sys_read(args[0], args[1] as *const u8, args[2]),
+        SYSCALL_DUP => sys_dup(args[0]),
+        SYSCALL_DUP3 => sys_dup3(args[0], args[1], args[2]),
+        SYSCALL_IOCTL => sys_ioctl(args[0], args[1], args[2]),
+        SYSCALL_OPENAT => sys_openat(args[0] as isize, args[1] as *const u8, args[2] as u32),
+        SYSCALL_CLOSE => sys_close(args[0]),
+        SYSCALL_PIPE2 => sys_pipe2(args[0] as *mut [i32; 2], args[1]),
+        SYSCALL_READ => sys_read(args[0], args[1] as *mut u8, args[2]),
         SYSCALL_WRITE => sys_write(args[0], args[1] as *const u8, args[2]),
         SYSCALL_EXIT => sys_exit(args[0] as i32),
+        SYSCALL_NANOSLEEP => sys_nanosleep(args[0] as *const TimeSpec),
+        SYSCALL_SYSLOG => sys_syslog(args[0], args[1] as *mut u8, args[2]),
         SYSCALL_YIELD => sys_yield(),
+        SYSCALL_KILL => sys_kill(args[0] as isize, args[1]),
+        SYSCALL_SIGACTION => sys_sigaction(args[0], args[1] as *const SignalAction, args[2] as *mut SignalAction),
+        SYSCALL_SIGPROCMASK => sys_sigprocmask(args[0], args[1] as *const u64, args[2] as *mut u64),
+        SYSCALL_SIGRETURN => sys_sigreturn(),
+        SYSCALL_SET_PRIORITY => sys_set_priority(args[0] as isize),
         SYSCALL_GET_TIME => sys_get_time(),
         SYSCALL_GETPID => sys_getpid(),
+        SYSCALL_SBRK => sys_sbrk(args[0] as i32),
+        SYSCALL_MUNMAP => sys_munmap(args[0], args[1]),
         SYSCALL_FORK => sys_fork(),
-        SYSCALL_EXEC => sys_exec(args[0] as *const u8),
+        SYSCALL_EXEC => sys_exec(args[0] as *const u8, args[1] as *const usize, args[2] as *const usize),
+        SYSCALL_MMAP => sys_mmap(args[0], args[1], args[2]),
+        SYSCALL_MPROTECT => sys_mprotect(args[0], args[1], args[2]),
         SYSCALL_WAITPID => sys_waitpid(args[0] as isize, args[1] as *mut i32),
-        _ => panic!("Unsupported syscall_id: {}", syscall_id),
+        SYSCALL_TRACE => sys_trace(args[0]),
+        _ => {
+            warn!("[kernel] Unsupported syscall_id: {}", syscall_id);
+            Err(SysError::ENOSYS)
+        }
     }
 }
\ No newline at end of file
@@ -1,6 +1,8 @@
-use alloc::sync::Arc;
+use alloc::{string::String, vec::Vec};
 
-use crate::{loader::get_app_data_by_name, mm::{translate_refmut, translated_str}, println, task::{add_task, current_task, current_user_token, exit_current_and_run_next, suspend_current_and_run_next}, timer::get_time_ms};
+use super::{SysError, SysResult};
+
+use crate::{config::USER_STACK_SIZE, fs::{open_file, OpenFlags}, loader::get_app_data_by_name, mm::{translate_refmut, translated_ref, translated_str, translated_str_bounded, MapPermission}, println, task::{add_task, block_current_and_run_next, insert_into_pid2task, user_stack_args_size, current_task, current_user_token, exit_current_and_run_next, suspend_current_and_run_next, TaskStatus, MAX_PRIORITY, MIN_PRIORITY}, timer::{add_timer, cancel_timer, get_time, get_time_ms, TimeSpec, NSEC_PER_SEC}};
 
 pub fn sys_exit(exit_code: i32) -> ! {
     println!("[kernel] Application exited with code {}", exit_code);
@@ -8,23 +10,76 @@ pub fn sys_exit(exit_code: i32) -> ! {
     panic!("Unreachable in sys_exit");
 }
 
-pub fn sys_yield() -> isize {
+pub fn sys_yield() -> SysResult {
     suspend_current_and_run_next();
-    0
+    Ok(0)
 }
 
 /// get time in milliseconds
-pub fn sys_get_time() -> isize {
-    get_time_ms() as isize
+pub fn sys_get_time() -> SysResult {
+    Ok(get_time_ms())
 }
 
-pub fn sys_getpid() -> isize {
-    current_task().unwrap().pid.0 as isize
+/// block the current task for the time given in `req`, return EINVAL if `req.nsec` is out of range
+/// and EINTR if a signal interrupts it
+pub fn sys_nanosleep(req: *const TimeSpec) -> SysResult {
+    let task = current_task().unwrap();
+    let req = {
+        let mut inner = task.inner_exclusive_access();
+        inner.memory_set.fault_in(req as usize, core::mem::size_of::<TimeSpec>(), false)?;
+        *translated_ref(inner.memory_set.token(), req)?
+    };
+    if req.nsec >= NSEC_PER_SEC {
+        return Err(SysError::EINVAL);
+    }
+    let deadline = get_time().saturating_add(req.to_ticks());
+    // a signal or the timer of an earlier interrupted sleep may wake us up before the deadline
+    while get_time() < deadline {
+        let mut inner = task.inner_exclusive_access();
+        if inner.signal_pending() {
+            drop(inner);
+            cancel_timer(&task);
+            return Err(SysError::EINTR);
+        }
+        // leave the ready queue until the timer wakes us up
+        inner.task_status = TaskStatus::Blocked;
+        drop(inner);
+        add_timer(deadline, task.clone());
+        block_current_and_run_next();
+    }
+    // a signal may have woken us up right before the deadline
+    cancel_timer(&task);
+    Ok(0)
 }
 
-pub fn sys_fork() -> isize {
+/// turn the syscall tracing of the current task on if `enable` is not 0, off otherwise.
+/// It's inherited by the children and kept through exec, return whether it was on
+pub fn sys_trace(enable: usize) -> SysResult {
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    let old = inner.trace;
+    inner.trace = enable != 0;
+    Ok(old as usize)
+}
+
+/// set the priority of the current task, it must be in [MIN_PRIORITY, MAX_PRIORITY],
+/// return the new priority
+pub fn sys_set_priority(prio: isize) -> SysResult {
+    if !(MIN_PRIORITY as isize..=MAX_PRIORITY as isize).contains(&prio) {
+        return Err(SysError::EINVAL);
+    }
+    current_task().unwrap().inner_exclusive_access().sched_info.priority = prio as usize;
+    Ok(prio as usize)
+}
+
+pub fn sys_getpid() -> SysResult {
+    Ok(current_task().unwrap().pid.0)
+}
+
+/// Return ENOMEM if there is not enough memory for the child
+pub fn sys_fork() -> SysResult {
     let current_task = current_task().unwrap();
-    let new_task = current_task.fork();
+    let new_task = current_task.fork().ok_or(SysError::ENOMEM)?;
     let new_pid = new_task.pid.0;
     // modify trap context of new_task, because it returns immediately after switching
     let trap_cx = new_task.inner_exclusive_access().get_trap_cx();
@@ -32,55 +87,166 @@ pub fn sys_fork() -> isize {
     // for child process, fork returns 0
     trap_cx.x[10] = 0;
     // add new task to scheduler
+    insert_into_pid2task(new_pid, new_task.clone());
     add_task(new_task);
-    new_pid as isize
+    Ok(new_pid)
+}
+
+/// Read a NULL-terminated array of string pointers from user space. `budget` is the room left
+/// on the user stack, each string takes its bytes, its NUL and its pointer from it.
+/// Return E2BIG as soon as it runs out, without copying the rest
+fn translated_str_array(token: usize, mut ptr: *const usize, budget: &mut usize) -> Result<Vec<String>, SysError> {
+    let mut strings = Vec::new();
+    if ptr.is_null() {
+        return Ok(strings);
+    }
+    loop {
+        let str_ptr = *translated_ref(token, ptr)?;
+        if str_ptr == 0 {
+            break;
+        }
+        let max_len = budget
+            .checked_sub(core::mem::size_of::<usize>() + 1)
+            .ok_or(SysError::E2BIG)?;
+        let string = translated_str_bounded(token, str_ptr as *const u8, max_len)?.ok_or(SysError::E2BIG)?;
+        *budget = max_len - string.len();
+        strings.push(string);
+        ptr = ptr.wrapping_add(1);
+    }
+    Ok(strings)
 }
 
-pub fn sys_exec(path: *const u8) -> isize {
+/// Return ENOENT if there is no file named `path`, ENOEXEC if it isn't an ELF that can be loaded,
+/// E2BIG if `args` and `envs` don't fit in the user stack.
+/// Return ENOMEM if there is no memory for the new space, the old one is kept then.
+/// `args` and `envs` are NULL-terminated arrays of string pointers, a NULL array is taken as empty.
+pub fn sys_exec(path: *const u8, args: *const usize, envs: *const usize) -> SysResult {
     let token = current_user_token();
-    let path = translated_str(token, path);
-    if let Some(data) = get_app_data_by_name(path.as_str()) {
+    let path = translated_str(token, path)?;
+    // the same sum as `user_stack_args_size`, counted while copying
+    let mut budget = USER_STACK_SIZE / 2 - user_stack_args_size(&[], &[]);
+    let args = translated_str_array(token, args, &mut budget)?;
+    let envs = translated_str_array(token, envs, &mut budget)?;
+    // the initramfs goes first, then the disk
+    let disk_data;
+    let data = match get_app_data_by_name(path.as_str()) {
+        Some(data) => data,
+        None => {
+            disk_data = open_file(path.as_str(), OpenFlags::RDONLY)?.read_all();
+            disk_data.as_slice()
+        }
+    };
+    let task = current_task().unwrap();
+    let argc = args.len();
+    task.exec(data, args, envs)?;
+    // a0 is overwritten by the return value of syscall
+    Ok(argc)
+}
+
+/// If there is not a child process whose pid is same as given, return ECHILD.
+/// Else block until one of the matching children exits and return its pid.
+/// Return EFAULT if `exit_code_ptr` is neither null nor writable, the child is not reaped then.
+/// Return EINTR if a signal comes while waiting, a child killed by a signal exits with -signum.
+pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32) -> SysResult {
+    loop {
         let task = current_task().unwrap();
-        task.exec(data);
-        0
-    } else {
-        -1
+        // find a child process
+
+        let mut inner = task.inner_exclusive_access();
+        // a signal wakes us up without taking us out of the queue
+        inner.wait_queue.remove(&task);
+        if !inner.children.iter().any(|p| pid == -1 || pid as usize == p.getpid()) {
+            // if there is not a pid that this sys_call is looking for, then return immediately
+            return Err(SysError::ECHILD);
+        }
+        // find all the zombie pid, which satisfies the requirement
+        let pair = inner.children.iter().enumerate().find(|(_, p)| {
+            p.inner_exclusive_access().is_zombie() && (pid == -1 || pid as usize == p.getpid())
+        });
+        if let Some((idx, _)) = pair {
+            // check the pointer before reaping the child, a null pointer means no need to store
+            let exit_code_ref = if exit_code_ptr.is_null() {
+                None
+            } else {
+                inner.memory_set.fault_in(exit_code_ptr as usize, core::mem::size_of::<i32>(), true)?;
+                Some(translate_refmut(inner.memory_set.token(), exit_code_ptr)?)
+            };
+            // the child is deallocated once the hart it exited on switches away from it
+            let child = inner.children.remove(idx);
+            let found_pid = child.getpid();
+            let exit_code = child.inner_exclusive_access().exit_code;
+            if let Some(exit_code_ref) = exit_code_ref {
+                *exit_code_ref = exit_code;
+            }
+            return Ok(found_pid);
+        }
+        if inner.signal_pending() {
+            return Err(SysError::EINTR);
+        }
+        // sleep until a child exits, then check again
+        inner.task_status = TaskStatus::Blocked;
+        inner.wait_queue.push(task.clone());
+        drop(inner);
+        drop(task);
+        block_current_and_run_next();
     }
 }
 
-/// If there is not a child process whose pid is same as given, return -1.
-/// Else if there is a child process but it is still running, return -2.
-pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32) -> isize {
-    let task = current_task().unwrap();
-    // find a child process
+/// change data segment size, return the old program break
+pub fn sys_sbrk(size: i32) -> SysResult {
+    current_task()
+        .unwrap()
+        .change_program_brk(size)
+        .ok_or(SysError::ENOMEM)
+}
 
-    let mut inner = task.inner_exclusive_access();
-    if !inner.children.iter().any(|p| pid == -1 || pid as usize == p.getpid()) {
-        // if there is not a pid that this sys_call is looking for, then return immediately
-        return -1;
+const PROT_READ: usize = 1 << 0;
+const PROT_WRITE: usize = 1 << 1;
+const PROT_EXEC: usize = 1 << 2;
+const PROT_MASK: usize = PROT_READ | PROT_WRITE | PROT_EXEC;
+
+/// translate PROT_* bits into MapPermission, PROT_NONE is not supported and W implies R
+fn prot_to_perm(prot: usize) -> Option<MapPermission> {
+    if prot & !PROT_MASK != 0 || prot & PROT_MASK == 0 {
+        return None;
     }
-    // find all the zombie pid, which satisfies the requirement
-    let pair = inner.children.iter().enumerate().find(|(_, p)| {
-        p.inner_exclusive_access().is_zombie() && (pid == -1 || pid as usize == p.getpid())
-    });
-    if let Some((idx, _)) = pair {
-        let child = inner.children.remove(idx);
-        // confirm that child will be deallocated after removing from children list
-        assert_eq!(Arc::strong_count(&child), 1);
-        let found_pid = child.getpid();
-        let exit_code = child.inner_exclusive_access().exit_code;
-        *translate_refmut(inner.memory_set.token(), exit_code_ptr) = exit_code;
-        found_pid as isize
+    // PROT_READ/WRITE/EXEC are exactly R/W/X shifted by one bit
+    let mut perm = MapPermission::from_bits_truncate((prot << 1) as u8);
+    if perm.contains(MapPermission::W) {
+        perm |= MapPermission::R;
+    }
+    Some(perm)
+}
+
+/// map an anonymous area, return its start address
+pub fn sys_mmap(start: usize, len: usize, prot: usize) -> SysResult {
+    let perm = prot_to_perm(prot).ok_or(SysError::EINVAL)?;
+    current_task()
+        .unwrap()
+        .inner_exclusive_access()
+        .memory_set
+        .mmap(start, len, perm)
+        .ok_or(SysError::EINVAL)
+}
+
+pub fn sys_munmap(start: usize, len: usize) -> SysResult {
+    if current_task().unwrap().inner_exclusive_access().memory_set.munmap(start, len) {
+        Ok(0)
     } else {
-        -2
+        Err(SysError::EINVAL)
     }
 }
 
-// /// change data segment size
-// pub fn sys_sbrk(size: i32) -> isize {
-//     if let Some(old_brk) = change_program_brk(size) {
-//         old_brk as isize
-//     } else {
-//         -1
-//     }
-// }
\ No newline at end of file
+pub fn sys_mprotect(start: usize, len: usize, prot: usize) -> SysResult {
+    let perm = prot_to_perm(prot).ok_or(SysError::EINVAL)?;
+    if current_task()
+        .unwrap()
+        .inner_exclusive_access()
+        .memory_set
+        .mprotect(start, len, perm)
+    {
+        Ok(0)
+    } else {
+        Err(SysError::EINVAL)
+    }
+}
@@ -0,0 +1,100 @@
+//! Syscalls of signals, the numbers and the layouts are the ones of Linux
+
+use super::{SysError, SysResult};
+
+use crate::{
+    mm::{copy_from_user, copy_to_user, translate_refmut, translated_ref},
+    task::{
+        current_task, force_current_signal, pid2task, restore_signal_frame, send_signal,
+        SignalAction, SignalFlags, SIGSEGV, UNCATCHABLE,
+    },
+};
+
+/// how `sys_sigprocmask` changes the blocked signals
+const SIG_BLOCK: usize = 0;
+const SIG_UNBLOCK: usize = 1;
+const SIG_SETMASK: usize = 2;
+
+/// send `signum` to the task `pid`, signal 0 only checks that it exists.
+/// There are no process groups, so `pid` must be positive
+pub fn sys_kill(pid: isize, signum: usize) -> SysResult {
+    if pid <= 0 {
+        return Err(SysError::EINVAL);
+    }
+    if signum != 0 && SignalFlags::from_signum(signum).is_none() {
+        return Err(SysError::EINVAL);
+    }
+    let task = pid2task(pid as usize).ok_or(SysError::ESRCH)?;
+    if signum != 0 {
+        send_signal(task, signum);
+    }
+    Ok(0)
+}
+
+/// set the action of `signum` if `act` isn't null, and store the old one in `oldact` if it isn't null.
+/// SIGKILL and SIGSTOP can't be caught or ignored
+pub fn sys_sigaction(signum: usize, act: *const SignalAction, oldact: *mut SignalAction) -> SysResult {
+    let signal = SignalFlags::from_signum(signum).ok_or(SysError::EINVAL)?;
+    if UNCATCHABLE.contains(signal) {
+        return Err(SysError::EINVAL);
+    }
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    let token = inner.get_user_token();
+    // read the new action first, `act` and `oldact` may be the same
+    let new_action = if act.is_null() {
+        None
+    } else {
+        inner.memory_set.fault_in(act as usize, core::mem::size_of::<SignalAction>(), false)?;
+        Some(copy_from_user(token, act)?)
+    };
+    if !oldact.is_null() {
+        inner.memory_set.fault_in(oldact as usize, core::mem::size_of::<SignalAction>(), true)?;
+        copy_to_user(token, oldact, &inner.signal_actions.get(signum))?;
+    }
+    if let Some(action) = new_action {
+        inner.signal_actions.set(signum, action);
+    }
+    Ok(0)
+}
+
+/// change the blocked signals with `set` as `how` says if `set` isn't null, and store the old
+/// ones in `oldset` if it isn't null. SIGKILL and SIGSTOP can't be blocked
+pub fn sys_sigprocmask(how: usize, set: *const u64, oldset: *mut u64) -> SysResult {
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    let token = inner.get_user_token();
+    let old = inner.signal_mask;
+    let mask = if set.is_null() {
+        None
+    } else {
+        inner.memory_set.fault_in(set as usize, core::mem::size_of::<u64>(), false)?;
+        let set = SignalFlags::from_bits_truncate(*translated_ref(token, set)?);
+        Some(match how {
+            SIG_BLOCK => old | set,
+            SIG_UNBLOCK => old - set,
+            SIG_SETMASK => set,
+            _ => return Err(SysError::EINVAL),
+        })
+    };
+    if !oldset.is_null() {
+        inner.memory_set.fault_in(oldset as usize, core::mem::size_of::<u64>(), true)?;
+        *translate_refmut(token, oldset)? = old.bits();
+    }
+    if let Some(mask) = mask {
+        inner.signal_mask = mask - UNCATCHABLE;
+    }
+    Ok(0)
+}
+
+/// return from a signal handler, called by the restorer. The registers and the blocked
+/// signals are put back from the frame on the user stack, a broken frame raises SIGSEGV
+pub fn sys_sigreturn() -> SysResult {
+    match restore_signal_frame() {
+        Some(a0) => Ok(a0),
+        None => {
+            force_current_signal(SIGSEGV);
+            Err(SysError::EFAULT)
+        }
+    }
+}
@@ -0,0 +1,57 @@
+//! Reading the kernel log buffer, the actions are numbered as `syslog(2)` of Linux
+
+use super::{SysError, SysResult};
+
+use crate::{log, mm::translated_byte_buffer_mut, task::current_task};
+
+/// read the last `len` bytes of the log
+const SYSLOG_ACTION_READ_ALL: usize = 3;
+/// read the last `len` bytes, then clear the log
+const SYSLOG_ACTION_READ_CLEAR: usize = 4;
+const SYSLOG_ACTION_CLEAR: usize = 5;
+/// set the max level to `len`, 0 is Off and 5 is Trace, unlike the console levels of Linux
+const SYSLOG_ACTION_CONSOLE_LEVEL: usize = 8;
+/// the number of bytes in the log
+const SYSLOG_ACTION_SIZE_UNREAD: usize = 9;
+/// the size of the ring buffer
+const SYSLOG_ACTION_SIZE_BUFFER: usize = 10;
+
+/// `buf` is only used by the read actions, CONSOLE_LEVEL returns the previous level
+pub fn sys_syslog(action: usize, buf: *mut u8, len: usize) -> SysResult {
+    match action {
+        SYSLOG_ACTION_READ_ALL | SYSLOG_ACTION_READ_CLEAR => {
+            let task = current_task().unwrap();
+            let mut inner = task.inner_exclusive_access();
+            inner.memory_set.fault_in(buf as usize, len, true)?;
+            let token = inner.get_user_token();
+            drop(inner);
+            let buffers = translated_byte_buffer_mut(token, buf, len)?;
+            let log = log::read_tail(len);
+            if action == SYSLOG_ACTION_READ_CLEAR {
+                log::clear();
+            }
+            let mut copied = 0;
+            for buffer in buffers {
+                let n = buffer.len().min(log.len() - copied);
+                buffer[..n].copy_from_slice(&log[copied..copied + n]);
+                copied += n;
+            }
+            Ok(copied)
+        }
+        SYSLOG_ACTION_CLEAR => {
+            log::clear();
+            Ok(0)
+        }
+        SYSLOG_ACTION_CONSOLE_LEVEL => {
+            let old = log::max_level();
+            if log::set_max_level(len) {
+                Ok(old)
+            } else {
+                Err(SysError::EINVAL)
+            }
+        }
+        SYSLOG_ACTION_SIZE_UNREAD => Ok(log::buffered_len()),
+        SYSLOG_ACTION_SIZE_BUFFER => Ok(log::LOG_BUF_SIZE),
+        _ => Err(SysError::EINVAL),
+    }
+}
@@ -0,0 +1,86 @@
+//! strace-like logging of the syscalls of the traced tasks, see `sys_trace`
+
+use alloc::{format, string::String, vec::Vec};
+use log::info;
+
+use crate::{mm::translated_str, task::current_user_token};
+
+use super::*;
+
+/// how an argument is shown
+#[derive(Clone, Copy)]
+enum Arg {
+    Int,
+    Hex,
+    /// a user pointer to a NULL-terminated string
+    Str,
+}
+
+use Arg::*;
+
+/// name and arguments of a syscall
+fn signature(syscall_id: usize) -> Option<(&'static str, &'static [Arg])> {
+    Some(match syscall_id {
+        SYSCALL_DUP => ("dup", &[Int]),
+        SYSCALL_DUP3 => ("dup3", &[Int, Int, Hex]),
+        SYSCALL_IOCTL => ("ioctl", &[Int, Hex, Hex]),
+        SYSCALL_OPENAT => ("openat", &[Int, Str, Hex]),
+        SYSCALL_CLOSE => ("close", &[Int]),
+        SYSCALL_PIPE2 => ("pipe2", &[Hex, Hex]),
+        SYSCALL_READ => ("read", &[Int, Hex, Int]),
+        SYSCALL_WRITE => ("write", &[Int, Hex, Int]),
+        SYSCALL_EXIT => ("exit", &[Int]),
+        SYSCALL_NANOSLEEP => ("nanosleep", &[Hex]),
+        SYSCALL_SYSLOG => ("syslog", &[Int, Hex, Int]),
+        SYSCALL_YIELD => ("sched_yield", &[]),
+        SYSCALL_KILL => ("kill", &[Int, Int]),
+        SYSCALL_SIGACTION => ("rt_sigaction", &[Int, Hex, Hex]),
+        SYSCALL_SIGPROCMASK => ("rt_sigprocmask", &[Int, Hex, Hex]),
+        SYSCALL_SIGRETURN => ("rt_sigreturn", &[]),
+        SYSCALL_SET_PRIORITY => ("set_priority", &[Int]),
+        SYSCALL_GET_TIME => ("get_time", &[]),
+        SYSCALL_GETPID => ("getpid", &[]),
+        SYSCALL_SBRK => ("sbrk", &[Int]),
+        SYSCALL_MUNMAP => ("munmap", &[Hex, Hex]),
+        SYSCALL_FORK => ("fork", &[]),
+        SYSCALL_EXEC => ("execve", &[Str, Hex, Hex]),
+        SYSCALL_MMAP => ("mmap", &[Hex, Hex, Hex]),
+        SYSCALL_MPROTECT => ("mprotect", &[Hex, Hex, Hex]),
+        SYSCALL_WAITPID => ("waitpid", &[Int, Hex]),
+        SYSCALL_TRACE => ("trace", &[Int]),
+        _ => return None,
+    })
+}
+
+/// format the call as `name(args)`, before it runs since exec replaces the strings
+pub fn format_call(syscall_id: usize, args: [usize; 3]) -> String {
+    let Some((name, kinds)) = signature(syscall_id) else {
+        return format!("syscall_{}({:#x}, {:#x}, {:#x})", syscall_id, args[0], args[1], args[2]);
+    };
+    let args: Vec<String> = kinds
+        .iter()
+        .zip(args)
+        .map(|(kind, arg)| match kind {
+            Int => format!("{}", arg as isize),
+            Hex => format!("{:#x}", arg),
+            Str => match translated_str(current_user_token(), arg as *const u8) {
+                Ok(s) => format!("{:?}", s),
+                Err(_) => format!("{:#x}", arg),
+            },
+        })
+        .collect();
+    format!("{}({})", name, args.join(", "))
+}
+
+/// log a finished call with its result and the ticks it took
+pub fn log_return(pid: usize, call: &str, result: &SysResult, ticks: usize) {
+    match result {
+        Ok(ret) => info!("[strace] pid {} {} = {} <{} ticks>", pid, call, ret, ticks),
+        Err(errno) => info!("[strace] pid {} {} = -1 {:?} <{} ticks>", pid, call, errno, ticks),
+    }
+}
+
+/// log a call that never returns
+pub fn log_noreturn(pid: usize, call: &str) {
+    info!("[strace] pid {} {} = ?", pid, call);
+}
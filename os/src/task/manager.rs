@@ -1,45 +1,63 @@
-use alloc::{collections::vec_deque::VecDeque, sync::Arc};
+use alloc::{collections::btree_map::BTreeMap, sync::Arc};
 use lazy_static::lazy_static;
 
-use crate::sync::UPSafeCell;
+use crate::sync::SpinNoIrqLock;
 
-use super::task::TaskControlBlock;
+use super::{scheduler::{DefaultScheduler, Scheduler}, task::TaskControlBlock};
 
+/// Holds the ready tasks, which one runs next is up to the scheduler
 pub struct TaskManager {
-    ready_queue: VecDeque<Arc<TaskControlBlock>>,
+    scheduler: DefaultScheduler,
 }
 
 impl TaskManager {
     /// Create an empty TaskManager
     pub fn new() -> Self {
         Self {
-            ready_queue: VecDeque::new(),
+            scheduler: DefaultScheduler::new(),
         }
     }
 
     /// Add a task to TaskManager
     pub fn add(&mut self, task: Arc<TaskControlBlock>) {
-        self.ready_queue.push_back(task);
+        self.scheduler.add(task);
     }
 
-    /// Remove the first task and return it, or None if TaskManager is empty
+    /// Remove the next task chosen by the scheduler, or None if TaskManager is empty
     pub fn fetch(&mut self) -> Option<Arc<TaskControlBlock>> {
-        self.ready_queue.pop_front()
+        self.scheduler.fetch()
     } 
 }
 
 lazy_static! {
-    pub static ref TASK_MANAGER: UPSafeCell<TaskManager> = unsafe {
-        UPSafeCell::new(TaskManager::new())
-    };
+    pub static ref TASK_MANAGER: SpinNoIrqLock<TaskManager> =
+        SpinNoIrqLock::new("TASK_MANAGER", TaskManager::new());
+    /// all the tasks that haven't exited, to find the target of a signal
+    static ref PID2TASK: SpinNoIrqLock<BTreeMap<usize, Arc<TaskControlBlock>>> =
+        SpinNoIrqLock::new("PID2TASK", BTreeMap::new());
 }
 
-/// Public interface to add task into VecDeque
+/// Public interface to add task into the scheduler
 pub fn add_task(task: Arc<TaskControlBlock>) {
-    TASK_MANAGER.exclusive_access().add(task);
+    TASK_MANAGER.lock().add(task);
 }
 
-/// Public interface to fetch task from VecDeque
+/// Public interface to fetch task from the scheduler
 pub fn fetch_task() -> Option<Arc<TaskControlBlock>> {
-    TASK_MANAGER.exclusive_access().fetch()
-}
\ No newline at end of file
+    TASK_MANAGER.lock().fetch()
+}
+
+/// Register a new task to be found by its pid
+pub fn insert_into_pid2task(pid: usize, task: Arc<TaskControlBlock>) {
+    PID2TASK.lock().insert(pid, task);
+}
+
+/// Unregister an exiting task
+pub fn remove_from_pid2task(pid: usize) {
+    PID2TASK.lock().remove(&pid);
+}
+
+/// Find a task which hasn't exited by its pid
+pub fn pid2task(pid: usize) -> Option<Arc<TaskControlBlock>> {
+    PID2TASK.lock().get(&pid).cloned()
+}
@@ -1,45 +1,103 @@
-use alloc::sync::Arc;
+use alloc::sync::{Arc, Weak};
 pub use context::TaskContext;
 use lazy_static::lazy_static;
-pub use manager::add_task;
+pub use manager::{add_task, insert_into_pid2task, pid2task};
 pub use processor::{
-    current_task, current_trap_cx, current_user_token, run_tasks, schedule, take_current_task,
-    Processor,
+    current_task, current_trap_cx, current_user_token, hart_id, run_tasks, schedule, Processor,
 };
-use task::{TaskControlBlock, TaskStatus};
+pub use scheduler::{MAX_PRIORITY, MIN_PRIORITY};
+pub use signal::{
+    force_current_signal, handle_signals, restore_signal_frame, send_signal, SignalAction,
+    SignalFlags, SIGILL, SIGINT, SIGSEGV, UNCATCHABLE,
+};
+pub use task::{user_stack_args_size, TaskControlBlock, TaskStatus};
+pub use wait_queue::WaitQueue;
 
-use crate::{loader::get_app_data_by_name, println, sbi::shutdown};
+use crate::{fs::{open_file, sync_fs, OpenFlags}, loader::get_app_data_by_name, println, sbi::shutdown};
 
 mod context;
 mod manager;
 mod pid;
 mod processor;
+mod scheduler;
+mod signal;
 mod switch;
 mod task;
+mod wait_queue;
 
 /// Suspend the current `Running` task and run the next task in task list.
+/// The task is pushed back to ready queue by `run_tasks` once its context is saved.
 pub fn suspend_current_and_run_next() {
-    // current running task
-    let task = take_current_task().unwrap();
-
-    // access current TCB exclusively
+    let task = current_task().unwrap();
     let mut task_inner = task.inner_exclusive_access();
     let task_cx_ptr = &mut task_inner.task_cx as *mut TaskContext;
-    // Change status to Ready
     task_inner.task_status = TaskStatus::Ready;
     drop(task_inner);
-    // release current PCB
-
-    // push back to ready queue
-    add_task(task);
+    drop(task);
     // jump to scheduling cycle (schedule to idle)
     schedule(task_cx_ptr);
 }
 
+/// Suspend the current task because its time slice is used up.
+pub fn preempt_current_and_run_next() {
+    current_task().unwrap().inner_exclusive_access().sched_info.ticks += 1;
+    suspend_current_and_run_next();
+}
+
+/// Block the current task and run the next task in task list.
+/// The caller must have set the task `Blocked` and put it into a wait queue under the same lock,
+/// so a wakeup from another hart can't be missed in between.
+pub fn block_current_and_run_next() {
+    let task = current_task().unwrap();
+    let task_cx_ptr = &mut task.inner_exclusive_access().task_cx as *mut TaskContext;
+    drop(task);
+    schedule(task_cx_ptr);
+}
+
+/// Set the current task `Blocked`, call it before putting the task into a wait queue.
+/// Return false and leave it running if a signal is pending, the caller returns EINTR then
+pub fn mark_current_blocked() -> bool {
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    if inner.signal_pending() {
+        return false;
+    }
+    inner.task_status = TaskStatus::Blocked;
+    true
+}
+
+/// a signal is going to be delivered to the current task
+pub fn current_signal_pending() -> bool {
+    current_task().unwrap().inner_exclusive_access().signal_pending()
+}
+
+/// Make a blocked task `Ready` and push it back to ready queue.
+/// If it is still switching out on another hart, `run_tasks` there pushes it instead.
+pub fn wakeup_task(task: Arc<TaskControlBlock>) {
+    let mut task_inner = task.inner_exclusive_access();
+    if task_inner.task_status != TaskStatus::Blocked {
+        return;
+    }
+    task_inner.task_status = TaskStatus::Ready;
+    let on_cpu = task_inner.on_cpu;
+    drop(task_inner);
+    if !on_cpu {
+        add_task(task);
+    }
+}
+
+/// Wake up the tasks waiting for a child of `task` to exit
+fn wakeup_waiters(task: &TaskControlBlock) {
+    let waiters = task.inner_exclusive_access().wait_queue.take_all();
+    for waiter in waiters {
+        wakeup_task(waiter);
+    }
+}
+
 pub const IDLE_PID: usize = 0;
 
 pub fn exit_current_and_run_next(exit_code: i32) {
-    let task = take_current_task().unwrap();
+    let task = current_task().unwrap();
 
     let pid = task.getpid();
     // if current exit task is IDLE_TASK
@@ -48,6 +106,7 @@ pub fn exit_current_and_run_next(exit_code: i32) {
             "[kernel] Idle process exit with exit_code {} ...",
             exit_code
         );
+        sync_fs();
         if exit_code != 0 {
             shutdown(true)
         } else {
@@ -55,29 +114,40 @@ pub fn exit_current_and_run_next(exit_code: i32) {
         }
     }
 
-    // Access current TCB exclusively
+    // signals can't find it any more
+    manager::remove_from_pid2task(pid);
+    // Access current TCB exclusively, only one lock is held at a time below to avoid deadlocks
     let mut inner = task.inner_exclusive_access();
-    // Change status to Zombie
-    inner.task_status = TaskStatus::Zombie;
+    let children = core::mem::take(&mut inner.children);
+    let parent = inner.parent.as_ref().and_then(Weak::upgrade);
+    // close all the opened files after releasing the lock, e.g. the write end of a pipe
+    let fd_table = core::mem::take(&mut inner.fd_table);
+    inner.memory_set.recycle_data_pages(); // deallocate user space
     // Record exit code
     inner.exit_code = exit_code;
+    // Change status to Zombie, the idle loop drops the task once its context is switched out
+    inner.task_status = TaskStatus::Zombie;
+    drop(inner);
 
-    // Access initproc TCB exclusively
-    {
-        // move the child task into initproc instead of its parent
-        let mut initproc_inner = INITPROC.inner_exclusive_access();
-        for child in inner.children.iter() {
-            child.inner_exclusive_access().parent = Some(Arc::downgrade(&INITPROC));
-            initproc_inner.children.push(child.clone());
-        }
+    // move the child task into initproc instead of its parent, a child exiting after its parent
+    // is changed wakes initproc itself, so it must be in the children of initproc by then
+    let mut orphan_zombie = false;
+    for child in children {
+        INITPROC.inner_exclusive_access().children.push(child.clone());
+        let mut child_inner = child.inner_exclusive_access();
+        child_inner.parent = Some(Arc::downgrade(&INITPROC));
+        orphan_zombie |= child_inner.is_zombie();
     }
-    // release the parent PCB
-
-    inner.children.clear();
-    inner.memory_set.recycle_data_pages(); // deallocate user space
-    drop(inner);
+    if orphan_zombie {
+        wakeup_waiters(&INITPROC);
+    }
+    // the parent may be blocked in sys_waitpid
+    if let Some(parent) = parent {
+        wakeup_waiters(&parent);
+    }
+    drop(fd_table);
     drop(task);
-    
+
     // no need to save the current task context, since it exited
     let mut _unused = TaskContext::zero_init();
     schedule(&mut _unused as *mut _);
@@ -85,12 +155,17 @@ pub fn exit_current_and_run_next(exit_code: i32) {
 
 lazy_static! {
     ///Globle process that init user shell
-    pub static ref INITPROC: Arc<TaskControlBlock> = Arc::new(TaskControlBlock::new(
-        get_app_data_by_name("initproc").unwrap()
-    ));
+    pub static ref INITPROC: Arc<TaskControlBlock> = Arc::new(match get_app_data_by_name("initproc") {
+        Some(data) => TaskControlBlock::new(data),
+        None => {
+            let inode = open_file("initproc", OpenFlags::RDONLY).unwrap();
+            TaskControlBlock::new(inode.read_all().as_slice())
+        }
+    });
 }
 
 ///Add init process to the manager
 pub fn add_initproc() {
+    insert_into_pid2task(INITPROC.getpid(), INITPROC.clone());
     add_task(INITPROC.clone());
 }
\ No newline at end of file
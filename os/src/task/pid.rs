@@ -1,13 +1,15 @@
+use core::arch::asm;
+
 use alloc::vec::Vec;
 use lazy_static::lazy_static;
 
-use crate::{config::{KERNEL_STACK_SIZE, PAGE_SIZE, TRAMPOLINE}, mm::{MapPermission, VirtAddr, KERNEL_SPACE}, sync::UPSafeCell};
+use crate::{board::hart_mask, config::{KERNEL_STACK_SIZE, PAGE_SIZE, TRAMPOLINE}, mm::{MapPermission, VirtAddr, KERNEL_SPACE}, sbi::remote_sfence_vma, sync::SpinNoIrqLock};
 
 pub struct PidHandle(pub usize);
 
 impl Drop for PidHandle {
     fn drop(&mut self) {
-        PID_ALLOCATOR.exclusive_access().dealloc(self.0);
+        PID_ALLOCATOR.lock().dealloc(self.0);
     }
 }
 
@@ -47,14 +49,13 @@ impl PidAllocator {
 }
 
 lazy_static! {
-    pub static ref PID_ALLOCATOR: UPSafeCell<PidAllocator> = unsafe {
-        UPSafeCell::new(PidAllocator::new())
-    };
+    pub static ref PID_ALLOCATOR: SpinNoIrqLock<PidAllocator> =
+        SpinNoIrqLock::new("PID_ALLOCATOR", PidAllocator::new());
 }
 
 ///Allocate a pid from PID_ALLOCATOR
 pub fn pid_alloc() -> PidHandle {
-    PID_ALLOCATOR.exclusive_access().alloc()
+    PID_ALLOCATOR.lock().alloc()
 }
 
 /// return (bottom, top) of a kernel stack in kernel space
@@ -70,18 +71,22 @@ pub struct KernelStack {
 }
 
 impl KernelStack {
-    ///Create a kernelstack from pid
-    pub fn new(pid_handle: &PidHandle) -> Self {
+    ///Create a kernelstack from pid, return None if there are not enough frames for it
+    pub fn new(pid_handle: &PidHandle) -> Option<Self> {
         let pid = pid_handle.0;
         let (kernel_stack_bottom, kernel_stack_top) = kernel_stack_position(pid);
-        KERNEL_SPACE.exclusive_access().insert_framed_area(
+        let mapped = KERNEL_SPACE.lock().insert_framed_area(
             kernel_stack_bottom.into(), 
             kernel_stack_top.into(),
             MapPermission::R | MapPermission::W
         );
-        KernelStack {
-            pid: pid_handle.0
+        // only built once the stack is mapped, dropping it unmaps the stack
+        if !mapped {
+            return None;
         }
+        Some(KernelStack {
+            pid: pid_handle.0
+        })
     }
 
     pub fn get_top(&self) -> usize {
@@ -107,6 +112,11 @@ impl Drop for KernelStack {
     fn drop(&mut self) {
         let (kernel_stack_bottom, _) = kernel_stack_position(self.pid);
         let kernel_stack_bottom_va: VirtAddr = kernel_stack_bottom.into();
-        KERNEL_SPACE.exclusive_access().remove_area_with_start_vpn(kernel_stack_bottom_va.into());
+        KERNEL_SPACE.lock().remove_area_with_start_vpn(kernel_stack_bottom_va.into());
+        // the stack is mapped again for the next task with this pid, other harts may still cache it
+        unsafe {
+            asm!("sfence.vma");
+        }
+        remote_sfence_vma(hart_mask(), kernel_stack_bottom, KERNEL_STACK_SIZE);
     }
 }
\ No newline at end of file
@@ -1,9 +1,13 @@
-use alloc::sync::Arc;
+use core::arch::asm;
+
+use alloc::{sync::Arc, vec::Vec};
 use lazy_static::lazy_static;
 
-use crate::{sync::UPSafeCell, trap::TrapContext};
+use riscv::register::sstatus;
+
+use crate::{config::MAX_HARTS, sync::SpinNoIrqLock, trap::{wait_for_interrupt, TrapContext}};
 
-use super::{context::TaskContext, manager::fetch_task, switch::__switch, task::{TaskControlBlock, TaskStatus}};
+use super::{context::TaskContext, manager::{add_task, fetch_task}, switch::__switch, task::{TaskControlBlock, TaskStatus}};
 
 /// Processor management structure
 pub struct Processor {
@@ -38,40 +42,63 @@ impl Processor {
 }
 
 lazy_static! {
-    pub static ref PROCESSOR: UPSafeCell<Processor> = unsafe {
-        UPSafeCell::new(Processor::new())
-    };
+    /// one processor per hart, a hart only touches its own one.
+    /// The lock keeps the interrupt handlers out while it's accessed
+    static ref PROCESSORS: Vec<SpinNoIrqLock<Processor>> = (0..MAX_HARTS)
+        .map(|_| SpinNoIrqLock::new("PROCESSOR", Processor::new()))
+        .collect();
+}
+
+/// the id of the current hart, which the kernel keeps in tp
+pub fn hart_id() -> usize {
+    let hart_id;
+    unsafe {
+        asm!("mv {}, tp", out(reg) hart_id);
+    }
+    hart_id
+}
+
+fn current_processor() -> &'static SpinNoIrqLock<Processor> {
+    &PROCESSORS[hart_id()]
 }
 
 ///The main part of process execution and scheduling
 ///Loop `fetch_task` to get the process that needs to run, and switch the process through `__switch`
 pub fn run_tasks() {
     loop {
-        let mut processor = PROCESSOR.exclusive_access();
         if let Some(task) = fetch_task() {
+            let mut processor = current_processor().lock();
             let idle_task_cx_ptr = processor.get_idle_task_cx_ptr();
             // access coming task TCB exclusively
             let mut task_inner = task.inner_exclusive_access();
             let next_task_cx_ptr = &task_inner.task_cx as *const TaskContext;
             task_inner.task_status = TaskStatus::Running;
+            task_inner.on_cpu = true;
             drop(task_inner);
             processor.current = Some(task);
             drop(processor);
             unsafe {
                 __switch(idle_task_cx_ptr, next_task_cx_ptr);
             }
+            // the context of the task is saved now, so another hart may run it
+            let task = current_processor().lock().take_current().unwrap();
+            let mut task_inner = task.inner_exclusive_access();
+            task_inner.on_cpu = false;
+            let ready = task_inner.task_status == TaskStatus::Ready;
+            drop(task_inner);
+            if ready {
+                add_task(task);
+            }
+        } else {
+            // sleep until a timer or a device wakes up some task
+            wait_for_interrupt();
         }
     }
 }
 
-///Take the current task,leaving a None in its place
-pub fn take_current_task() -> Option<Arc<TaskControlBlock>> {
-    PROCESSOR.exclusive_access().take_current()
-}
-
 ///Get running task
 pub fn current_task() -> Option<Arc<TaskControlBlock>> {
-    PROCESSOR.exclusive_access().current()
+    current_processor().lock().current()
 }
 
 ///Get token of the address space of current task
@@ -86,12 +113,20 @@ pub fn current_trap_cx() -> &'static mut TrapContext {
     current_task().unwrap().inner_exclusive_access().get_trap_cx()
 }
 
-///Return to idle control flow for new scheduling
+///Return to idle control flow for new scheduling.
+///The idle control flow runs with interrupts disabled, they are enabled again once the task is back
 pub fn schedule(switched_task_cx_ptr: *mut TaskContext) {
-    let mut processor = PROCESSOR.exclusive_access();
+    let sie = sstatus::read().sie();
+    unsafe {
+        sstatus::clear_sie();
+    }
+    let mut processor = current_processor().lock();
     let idle_task_cx_ptr = processor.get_idle_task_cx_ptr();
     drop(processor);
     unsafe {
         __switch(switched_task_cx_ptr, idle_task_cx_ptr);
+        if sie {
+            sstatus::set_sie();
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,63 @@
+use alloc::{collections::vec_deque::VecDeque, sync::Arc};
+
+use super::{super::task::TaskControlBlock, Scheduler};
+
+const LEVELS: usize = 3;
+/// move every task back to the top level after this many fetches, so that nobody starves
+const BOOST_INTERVAL: usize = 500;
+
+/// time slice of a level in timer ticks, lower levels run longer but less often
+fn time_slice(level: usize) -> usize {
+    1 << level
+}
+
+/// Multi-level feedback queue: a task that uses up the time slice of its level drops one level,
+/// the one that blocks or yields early stays, so interactive tasks keep a high priority
+pub struct MlfqScheduler {
+    queues: [VecDeque<Arc<TaskControlBlock>>; LEVELS],
+    fetched: usize,
+}
+
+impl MlfqScheduler {
+    pub fn new() -> Self {
+        Self {
+            queues: [const { VecDeque::new() }; LEVELS],
+            fetched: 0,
+        }
+    }
+
+    fn boost(&mut self) {
+        for level in 1..LEVELS {
+            while let Some(task) = self.queues[level].pop_front() {
+                let mut inner = task.inner_exclusive_access();
+                inner.sched_info.level = 0;
+                inner.sched_info.ticks = 0;
+                drop(inner);
+                self.queues[0].push_back(task);
+            }
+        }
+    }
+}
+
+impl Scheduler for MlfqScheduler {
+    fn add(&mut self, task: Arc<TaskControlBlock>) {
+        let level = {
+            let mut inner = task.inner_exclusive_access();
+            let info = &mut inner.sched_info;
+            if info.ticks >= time_slice(info.level) {
+                info.level = (info.level + 1).min(LEVELS - 1);
+                info.ticks = 0;
+            }
+            info.level
+        };
+        self.queues[level].push_back(task);
+    }
+
+    fn fetch(&mut self) -> Option<Arc<TaskControlBlock>> {
+        self.fetched += 1;
+        if self.fetched % BOOST_INTERVAL == 0 {
+            self.boost();
+        }
+        self.queues.iter_mut().find_map(|queue| queue.pop_front())
+    }
+}
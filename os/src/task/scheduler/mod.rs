@@ -0,0 +1,72 @@
+use alloc::sync::Arc;
+
+use super::task::TaskControlBlock;
+
+#[cfg(feature = "sched-mlfq")]
+mod mlfq;
+#[cfg(not(any(feature = "sched-stride", feature = "sched-mlfq")))]
+mod rr;
+#[cfg(feature = "sched-stride")]
+mod stride;
+
+#[cfg(any(
+    all(feature = "sched-rr", feature = "sched-stride"),
+    all(feature = "sched-rr", feature = "sched-mlfq"),
+    all(feature = "sched-stride", feature = "sched-mlfq"),
+))]
+compile_error!("choose only one of the features sched-rr, sched-stride and sched-mlfq");
+
+/// The policy picked by cargo feature, round-robin if none is given
+#[cfg(feature = "sched-mlfq")]
+pub type DefaultScheduler = mlfq::MlfqScheduler;
+#[cfg(not(any(feature = "sched-stride", feature = "sched-mlfq")))]
+pub type DefaultScheduler = rr::RoundRobinScheduler;
+#[cfg(feature = "sched-stride")]
+pub type DefaultScheduler = stride::StrideScheduler;
+
+/// The ready queue of a scheduling policy
+pub trait Scheduler {
+    /// A task becomes ready to run
+    fn add(&mut self, task: Arc<TaskControlBlock>);
+    /// Pick the next task to run, or None if no task is ready
+    fn fetch(&mut self) -> Option<Arc<TaskControlBlock>>;
+}
+
+pub const DEFAULT_PRIORITY: usize = 16;
+/// stride requires a priority of at least 2 to keep the passes comparable
+pub const MIN_PRIORITY: usize = 2;
+/// stride requires a priority of at most its BIG_STRIDE, so that every stride is at least 1
+pub const MAX_PRIORITY: usize = 1 << 20;
+
+/// Per-task bookkeeping of the schedulers
+#[allow(unused)]
+#[derive(Clone, Copy)]
+pub struct SchedInfo {
+    /// set by `sys_set_priority`, only stride makes use of it
+    pub priority: usize,
+    /// the virtual time consumed, for stride
+    pub pass: usize,
+    /// the queue the task is in, for mlfq
+    pub level: usize,
+    /// timer ticks used up in the current level, for mlfq
+    pub ticks: usize,
+}
+
+impl SchedInfo {
+    pub fn new() -> Self {
+        Self {
+            priority: DEFAULT_PRIORITY,
+            pass: 0,
+            level: 0,
+            ticks: 0,
+        }
+    }
+
+    /// a forked child starts afresh but keeps the priority of its parent
+    pub fn fork(&self) -> Self {
+        Self {
+            priority: self.priority,
+            ..Self::new()
+        }
+    }
+}
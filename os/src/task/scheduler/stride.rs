@@ -0,0 +1,58 @@
+use alloc::{collections::vec_deque::VecDeque, sync::Arc};
+
+use super::{super::task::TaskControlBlock, Scheduler};
+
+/// the stride of a task is BIG_STRIDE / priority
+const BIG_STRIDE: usize = 1 << 20;
+
+/// Stride scheduling: run the task with the smallest pass and advance its pass by its stride,
+/// so that the CPU share of a task is proportional to its priority
+pub struct StrideScheduler {
+    ready_queue: VecDeque<Arc<TaskControlBlock>>,
+    /// pass of the task fetched last, tasks coming back from a sleep start from here
+    current_pass: usize,
+}
+
+/// compare two passes which may have wrapped around, they never differ by more than BIG_STRIDE / 2
+fn pass_less(a: usize, b: usize) -> bool {
+    (a.wrapping_sub(b) as isize) < 0
+}
+
+impl StrideScheduler {
+    pub fn new() -> Self {
+        Self {
+            ready_queue: VecDeque::new(),
+            current_pass: 0,
+        }
+    }
+}
+
+impl Scheduler for StrideScheduler {
+    fn add(&mut self, task: Arc<TaskControlBlock>) {
+        {
+            let mut inner = task.inner_exclusive_access();
+            // don't let a blocked or new task bank the time it didn't run
+            if pass_less(inner.sched_info.pass, self.current_pass) {
+                inner.sched_info.pass = self.current_pass;
+            }
+        }
+        self.ready_queue.push_back(task);
+    }
+
+    fn fetch(&mut self) -> Option<Arc<TaskControlBlock>> {
+        let (idx, _) = self
+            .ready_queue
+            .iter()
+            .map(|task| task.inner_exclusive_access().sched_info.pass)
+            .enumerate()
+            .reduce(|min, next| if pass_less(next.1, min.1) { next } else { min })?;
+        let task = self.ready_queue.remove(idx).unwrap();
+        {
+            let mut inner = task.inner_exclusive_access();
+            self.current_pass = inner.sched_info.pass;
+            let stride = (BIG_STRIDE / inner.sched_info.priority).max(1);
+            inner.sched_info.pass = inner.sched_info.pass.wrapping_add(stride);
+        }
+        Some(task)
+    }
+}
@@ -0,0 +1,267 @@
+//! POSIX-like signals. A signal stays pending in the task until it's about to return to
+//! user space, then `handle_signals` runs its handler or takes the default action
+
+use alloc::sync::Arc;
+use bitflags::bitflags;
+
+use crate::{mm::{copy_from_user, copy_to_user}, println};
+
+use super::{current_task, exit_current_and_run_next, task::{TaskControlBlock, TaskControlBlockInner, TaskStatus}, wakeup_task};
+
+/// the largest signal number, there are no real-time signals
+pub const MAX_SIG: usize = 31;
+
+bitflags! {
+    /// a set of signals, signal n is bit n - 1 like `sigset_t` of Linux
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct SignalFlags: u64 {
+        const SIGHUP = 1 << 0;
+        const SIGINT = 1 << 1;
+        const SIGQUIT = 1 << 2;
+        const SIGILL = 1 << 3;
+        const SIGTRAP = 1 << 4;
+        const SIGABRT = 1 << 5;
+        const SIGBUS = 1 << 6;
+        const SIGFPE = 1 << 7;
+        const SIGKILL = 1 << 8;
+        const SIGUSR1 = 1 << 9;
+        const SIGSEGV = 1 << 10;
+        const SIGUSR2 = 1 << 11;
+        const SIGPIPE = 1 << 12;
+        const SIGALRM = 1 << 13;
+        const SIGTERM = 1 << 14;
+        const SIGSTKFLT = 1 << 15;
+        const SIGCHLD = 1 << 16;
+        const SIGCONT = 1 << 17;
+        const SIGSTOP = 1 << 18;
+        const SIGTSTP = 1 << 19;
+        const SIGTTIN = 1 << 20;
+        const SIGTTOU = 1 << 21;
+        const SIGURG = 1 << 22;
+        const SIGXCPU = 1 << 23;
+        const SIGXFSZ = 1 << 24;
+        const SIGVTALRM = 1 << 25;
+        const SIGPROF = 1 << 26;
+        const SIGWINCH = 1 << 27;
+        const SIGIO = 1 << 28;
+        const SIGPWR = 1 << 29;
+        const SIGSYS = 1 << 30;
+    }
+}
+
+pub const SIGINT: usize = 2;
+pub const SIGILL: usize = 4;
+pub const SIGSEGV: usize = 11;
+
+/// the signals that can't be caught, blocked or ignored
+pub const UNCATCHABLE: SignalFlags = SignalFlags::SIGKILL.union(SignalFlags::SIGSTOP);
+
+/// The signals whose default action is to do nothing.
+/// There is no job control, so the ones to stop and continue a task are ignored as well
+const DEFAULT_IGNORED: SignalFlags = SignalFlags::SIGCHLD
+    .union(SignalFlags::SIGCONT)
+    .union(SignalFlags::SIGSTOP)
+    .union(SignalFlags::SIGTSTP)
+    .union(SignalFlags::SIGTTIN)
+    .union(SignalFlags::SIGTTOU)
+    .union(SignalFlags::SIGURG)
+    .union(SignalFlags::SIGWINCH);
+
+impl SignalFlags {
+    /// the set of the signal `signum`, None if it isn't in [1, MAX_SIG]
+    pub fn from_signum(signum: usize) -> Option<Self> {
+        (1..=MAX_SIG)
+            .contains(&signum)
+            .then(|| Self::from_bits_retain(1 << (signum - 1)))
+    }
+
+    /// the smallest signal number in the set
+    fn first(self) -> Option<usize> {
+        (!self.is_empty()).then(|| self.bits().trailing_zeros() as usize + 1)
+    }
+}
+
+/// take the default action
+pub const SIG_DFL: usize = 0;
+/// discard the signal
+pub const SIG_IGN: usize = 1;
+
+/// The layout of `struct sigaction` of Linux with `sa_restorer`, the handler returns to
+/// the restorer, which calls `sigreturn`. The flags are kept but none of them is supported
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct SignalAction {
+    pub handler: usize,
+    pub flags: usize,
+    pub restorer: usize,
+    /// blocked in addition to the signal itself while the handler runs
+    pub mask: u64,
+}
+
+impl SignalAction {
+    const fn default() -> Self {
+        Self {
+            handler: SIG_DFL,
+            flags: 0,
+            restorer: 0,
+            mask: 0,
+        }
+    }
+}
+
+/// the action of each signal, indexed by the signal number
+#[derive(Clone)]
+pub struct SignalActions {
+    table: [SignalAction; MAX_SIG + 1],
+}
+
+impl SignalActions {
+    pub fn new() -> Self {
+        Self {
+            table: [SignalAction::default(); MAX_SIG + 1],
+        }
+    }
+
+    pub fn get(&self, signum: usize) -> SignalAction {
+        self.table[signum]
+    }
+
+    pub fn set(&mut self, signum: usize, action: SignalAction) {
+        self.table[signum] = action;
+    }
+
+    /// the handlers are gone with the old program on exec, the ignored signals stay ignored
+    pub fn reset_handlers(&mut self) {
+        for action in self.table.iter_mut().filter(|action| action.handler != SIG_IGN) {
+            *action = SignalAction::default();
+        }
+    }
+
+    /// whether `signum` is discarded instead of being delivered
+    fn is_ignored(&self, signum: usize) -> bool {
+        match self.table[signum].handler {
+            SIG_IGN => true,
+            SIG_DFL => DEFAULT_IGNORED.contains(SignalFlags::from_signum(signum).unwrap()),
+            _ => false,
+        }
+    }
+}
+
+/// saved on the user stack while a handler runs, `sigreturn` puts it back
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct SignalFrame {
+    x: [usize; 32],
+    sepc: usize,
+    /// the blocked signals before the handler
+    mask: u64,
+}
+
+/// Make `signum` pending in `task`, it's discarded at once if ignored.
+/// A blocked task is woken up, its syscall returns EINTR unless the signal is blocked
+pub fn send_signal(task: Arc<TaskControlBlock>, signum: usize) {
+    let signal = SignalFlags::from_signum(signum).unwrap();
+    let mut inner = task.inner_exclusive_access();
+    if inner.is_zombie() || inner.signal_actions.is_ignored(signum) {
+        return;
+    }
+    inner.signals |= signal;
+    let wake = inner.task_status == TaskStatus::Blocked && !inner.signal_mask.contains(signal);
+    drop(inner);
+    if wake {
+        wakeup_task(task);
+    }
+}
+
+/// Raise `signum` for a fault of the current task, which would run into it again if the
+/// signal were blocked or ignored, so the default action is taken in that case
+pub fn force_current_signal(signum: usize) {
+    let signal = SignalFlags::from_signum(signum).unwrap();
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    if inner.signal_mask.contains(signal) || inner.signal_actions.get(signum).handler == SIG_IGN {
+        inner.signal_mask.remove(signal);
+        inner.signal_actions.set(signum, SignalAction::default());
+    }
+    inner.signals |= signal;
+}
+
+/// exit with -signum, the exit code of a task killed by a signal
+fn terminate_current(signum: usize) -> ! {
+    println!("[kernel] Application killed by signal {}", signum);
+    exit_current_and_run_next(-(signum as i32));
+    panic!("Unreachable in terminate_current");
+}
+
+/// Push a frame on the user stack and make the task return to the handler of `signum`
+/// with the signal number in a0, return false if the stack is unusable
+fn enter_handler(inner: &mut TaskControlBlockInner, signum: usize, action: &SignalAction) -> bool {
+    let cx = inner.get_trap_cx();
+    let frame = SignalFrame {
+        x: cx.x,
+        sepc: cx.sepc,
+        mask: inner.signal_mask.bits(),
+    };
+    // the ABI requires sp to be 16-byte aligned
+    let sp = cx.x[2].wrapping_sub(core::mem::size_of::<SignalFrame>()) & !0xf;
+    if inner.memory_set.fault_in(sp, core::mem::size_of::<SignalFrame>(), true).is_err() {
+        return false;
+    }
+    if copy_to_user(inner.get_user_token(), sp as *mut SignalFrame, &frame).is_err() {
+        return false;
+    }
+    inner.signal_mask |= SignalFlags::from_bits_truncate(action.mask) | SignalFlags::from_signum(signum).unwrap();
+    inner.signal_mask -= UNCATCHABLE;
+    cx.x[2] = sp;
+    cx.x[1] = action.restorer;
+    cx.x[10] = signum;
+    cx.sepc = action.handler;
+    true
+}
+
+/// Deliver the pending signals that aren't blocked, called right before returning to user space.
+/// A handler is entered for the first caught one, the others wait for the next return
+pub fn handle_signals() {
+    loop {
+        let task = current_task().unwrap();
+        let mut inner = task.inner_exclusive_access();
+        let Some(signum) = (inner.signals - inner.signal_mask).first() else {
+            return;
+        };
+        inner.signals.remove(SignalFlags::from_signum(signum).unwrap());
+        let action = inner.signal_actions.get(signum);
+        match action.handler {
+            SIG_IGN => continue,
+            SIG_DFL if inner.signal_actions.is_ignored(signum) => continue,
+            SIG_DFL => {
+                drop(inner);
+                drop(task);
+                terminate_current(signum);
+            }
+            _ => {
+                if enter_handler(&mut inner, signum, &action) {
+                    return;
+                }
+                // nowhere to run the handler, e.g. the stack overflowed
+                drop(inner);
+                drop(task);
+                terminate_current(SIGSEGV);
+            }
+        }
+    }
+}
+
+/// Return from a handler, the frame is expected at the user sp, where `enter_handler` left it.
+/// Return the restored a0 so that the syscall leaves it as it was, None if the frame is unreadable
+pub fn restore_signal_frame() -> Option<usize> {
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    let cx = inner.get_trap_cx();
+    let sp = cx.x[2];
+    inner.memory_set.fault_in(sp, core::mem::size_of::<SignalFrame>(), false).ok()?;
+    let frame = copy_from_user(inner.get_user_token(), sp as *const SignalFrame).ok()?;
+    cx.x = frame.x;
+    cx.sepc = frame.sepc;
+    inner.signal_mask = SignalFlags::from_bits_truncate(frame.mask) - UNCATCHABLE;
+    Some(cx.x[10])
+}
@@ -1,23 +1,25 @@
-use core::cell::RefMut;
+use alloc::{string::String, sync::{Arc, Weak}, vec, vec::Vec};
 
-use alloc::{sync::{Arc, Weak}, vec::Vec};
+use crate::{config::{MAX_FD, MMAP_BASE, PAGE_SIZE, TRAP_CONTEXT}, fs::{File, Stdin, Stdout}, mm::{translated_byte_buffer_mut, MapPermission, MemorySet, PageFaultError, PhysPageNum, VirtAddr, KERNEL_SPACE}, sync::{SpinNoIrqLock, SpinNoIrqLockGuard}, syscall::SysError, trap::{trap_handler, TrapContext}};
 
-use crate::{config::TRAP_CONTEXT, mm::{MapPermission, MemorySet, PhysPageNum, VirtAddr, KERNEL_SPACE}, sync::UPSafeCell, trap::{trap_handler, TrapContext}};
-
-use super::{context::TaskContext, pid::{pid_alloc, KernelStack, PidHandle}};
+use super::{context::TaskContext, pid::{pid_alloc, KernelStack, PidHandle}, scheduler::SchedInfo, signal::{SignalActions, SignalFlags}, wait_queue::WaitQueue};
 
 #[derive(Clone, Copy, PartialEq)]
 pub enum TaskStatus {
     Ready,
     Running,
+    /// waiting for an event, not in the ready queue
+    Blocked,
     Zombie,
 }
 
 pub struct TaskControlBlock {
-    pub pid: PidHandle,
+    /// Fields are dropped in declaration order, the stack has to be unmapped before the pid
+    /// is freed, or a fork on another hart may reuse the pid and map its stack over this one
     pub kernel_stack: KernelStack,
+    pub pid: PidHandle,
     // mutable
-    inner: UPSafeCell<TaskControlBlockInner>,
+    inner: SpinNoIrqLock<TaskControlBlockInner>,
 }
 
 /// store the info needed in a task
@@ -27,10 +29,28 @@ pub struct TaskControlBlockInner {
     pub base_size: usize,
     pub task_cx: TaskContext,
     pub task_status: TaskStatus,
+    /// still running on a hart, its context isn't saved until the hart is back in `run_tasks`
+    pub on_cpu: bool,
+    pub sched_info: SchedInfo,
     pub memory_set: MemorySet,
     pub parent: Option<Weak<TaskControlBlock>>,
     pub children: Vec<Arc<TaskControlBlock>>,
+    /// opened files indexed by fd, None means a closed fd
+    pub fd_table: Vec<Option<Arc<dyn File>>>,
+    /// tasks blocked in `sys_waitpid` until one of the children exits
+    pub wait_queue: WaitQueue,
     pub exit_code: i32,
+    /// bottom of the heap area, fixed once the program is loaded
+    pub heap_bottom: usize,
+    /// current program break
+    pub program_brk: usize,
+    /// log the syscalls of this task, see `sys_trace`
+    pub trace: bool,
+    /// signals sent but not delivered yet
+    pub signals: SignalFlags,
+    /// blocked signals stay pending until they are unblocked
+    pub signal_mask: SignalFlags,
+    pub signal_actions: SignalActions,
 }
 
 impl TaskControlBlockInner {
@@ -49,50 +69,157 @@ impl TaskControlBlockInner {
     pub fn is_zombie(&self) -> bool {
         self.get_status() == TaskStatus::Zombie
     }
+
+    /// a signal is going to be delivered, blocking syscalls return EINTR then
+    pub fn signal_pending(&self) -> bool {
+        !(self.signals - self.signal_mask).is_empty()
+    }
+
+    /// find the lowest free fd, return None if there are already MAX_FD opened files
+    pub fn alloc_fd(&mut self) -> Option<usize> {
+        if let Some(fd) = self.fd_table.iter().position(Option::is_none) {
+            Some(fd)
+        } else if self.fd_table.len() < MAX_FD {
+            self.fd_table.push(None);
+            Some(self.fd_table.len() - 1)
+        } else {
+            None
+        }
+    }
+
+    /// get the file opened as `fd`
+    pub fn get_file(&self, fd: usize) -> Option<Arc<dyn File>> {
+        self.fd_table.get(fd).cloned().flatten()
+    }
+}
+
+/// auxv entry types, see `include/uapi/linux/auxvec.h`
+const AT_NULL: usize = 0;
+const AT_PAGESZ: usize = 6;
+
+/// Size of the initial stack image `init_user_stack` builds, without the alignment padding
+pub fn user_stack_args_size(args: &[String], envs: &[String]) -> usize {
+    let strings: usize = args.iter().chain(envs.iter()).map(|s| s.len() + 1).sum();
+    // argc, argv with NULL, envp with NULL and two auxv pairs
+    let words = 1 + (args.len() + 1) + (envs.len() + 1) + 4;
+    strings + words * core::mem::size_of::<usize>()
+}
+
+/// Lay out the initial user stack in the System V way, from the low address to the high:
+/// argc, argv[], NULL, envp[], NULL, auxv pairs ending with AT_NULL, then the strings.
+/// Return the new user sp, argv and envp, fail if there is no frame left for the stack.
+fn init_user_stack(
+    memory_set: &mut MemorySet,
+    user_sp: usize,
+    args: &[String],
+    envs: &[String],
+) -> Result<(usize, usize, usize), PageFaultError> {
+    const WORD: usize = core::mem::size_of::<usize>();
+    let strings_len: usize = args.iter().chain(envs.iter()).map(|s| s.len() + 1).sum();
+    let strings_start = user_sp - strings_len;
+    let mut strings = Vec::with_capacity(strings_len);
+    let mut string_ptrs = Vec::with_capacity(args.len() + envs.len());
+    for s in args.iter().chain(envs.iter()) {
+        string_ptrs.push(strings_start + strings.len());
+        strings.extend_from_slice(s.as_bytes());
+        strings.push(0);
+    }
+    let (arg_ptrs, env_ptrs) = string_ptrs.split_at(args.len());
+
+    let mut words = vec![args.len()];
+    words.extend_from_slice(arg_ptrs);
+    words.push(0);
+    words.extend_from_slice(env_ptrs);
+    words.push(0);
+    words.extend_from_slice(&[AT_PAGESZ, PAGE_SIZE, AT_NULL, 0]);
+    // the ABI requires sp to be 16-byte aligned
+    let sp = (strings_start - words.len() * WORD) & !0xf;
+
+    let mut image = vec![0u8; user_sp - sp];
+    for (i, word) in words.iter().enumerate() {
+        image[i * WORD..(i + 1) * WORD].copy_from_slice(&word.to_ne_bytes());
+    }
+    image[strings_start - sp..].copy_from_slice(&strings);
+
+    // the user stack is lazily mapped, the kernel writes it through physical addresses
+    memory_set.fault_in(sp, image.len(), true)?;
+    let buffers = translated_byte_buffer_mut(memory_set.token(), sp as *mut u8, image.len()).unwrap();
+    let mut offset = 0;
+    for buffer in buffers {
+        buffer.copy_from_slice(&image[offset..offset + buffer.len()]);
+        offset += buffer.len();
+    }
+    Ok((sp, sp + WORD, sp + (args.len() + 2) * WORD))
 }
 
 impl TaskControlBlock {
-    pub fn inner_exclusive_access(&self) -> RefMut<'_, TaskControlBlockInner> {
-        self.inner.exclusive_access()
+    pub fn inner_exclusive_access(&self) -> SpinNoIrqLockGuard<'_, TaskControlBlockInner> {
+        self.inner.lock()
     }
 
     pub fn new(elf_data: &[u8]) -> Self {
-        let (memory_set, user_sp, entry_point) = MemorySet::from_elf(elf_data);
+        let (mut memory_set, user_sp, entry_point) = MemorySet::from_elf(elf_data)
+            .expect("failed to load initproc");
+        let heap_bottom = user_sp;
+        let (user_sp, argv, envp) = init_user_stack(&mut memory_set, user_sp, &[], &[])
+            .expect("no frame left for the user stack of initproc");
         let trap_cx_ppn = memory_set.translate(VirtAddr::from(TRAP_CONTEXT).into()).unwrap().ppn();
         // allocate a pid and a kernel stack in corresponding space
         let pid_handle = pid_alloc();
-        let kernel_stack = KernelStack::new(&pid_handle);
+        let kernel_stack = KernelStack::new(&pid_handle).expect("no frame left for the kernel stack of initproc");
         let kernel_stack_top = kernel_stack.get_top();
         // push a task context, which goes to trap_return to the top of kernel stack
         let task_control_block = Self {
             pid: pid_handle,
             kernel_stack,
-            inner: unsafe {
-                UPSafeCell::new(TaskControlBlockInner {
-                    trap_cx_ppn,
-                    base_size: user_sp,
-                    task_cx: TaskContext::goto_trap_return(kernel_stack_top),
-                    task_status: TaskStatus::Ready,
-                    memory_set,
-                    parent: None,
-                    children: Vec::new(),
-                    exit_code: 0,
-                })
-            },
+            inner: SpinNoIrqLock::new("TCB", TaskControlBlockInner {
+                trap_cx_ppn,
+                base_size: heap_bottom,
+                task_cx: TaskContext::goto_trap_return(kernel_stack_top),
+                task_status: TaskStatus::Ready,
+                on_cpu: false,
+                sched_info: SchedInfo::new(),
+                memory_set,
+                parent: None,
+                children: Vec::new(),
+                wait_queue: WaitQueue::new(),
+                fd_table: vec![
+                    // 0 -> stdin
+                    Some(Arc::new(Stdin)),
+                    // 1 -> stdout
+                    Some(Arc::new(Stdout)),
+                    // 2 -> stderr
+                    Some(Arc::new(Stdout)),
+                ],
+                exit_code: 0,
+                heap_bottom,
+                program_brk: heap_bottom,
+                trace: false,
+                signals: SignalFlags::empty(),
+                signal_mask: SignalFlags::empty(),
+                signal_actions: SignalActions::new(),
+            }),
         };
         let trap_cx = task_control_block.inner_exclusive_access().get_trap_cx();
         *trap_cx = TrapContext::app_init_context(
             entry_point, 
             user_sp, 
-            KERNEL_SPACE.exclusive_access().token(), 
+            KERNEL_SPACE.lock().token(), 
             kernel_stack_top, 
             trap_handler as usize,
         );
+        trap_cx.x[10] = 0;
+        trap_cx.x[11] = argv;
+        trap_cx.x[12] = envp;
         task_control_block
     }
 
-    pub fn exec(&self, elf_data: &[u8]) {
-        let (memory_set, user_sp, entry_point) = MemorySet::from_elf(elf_data);
+    /// replace the user space with `elf_data`, `args` and `envs` are copied onto the new user stack.
+    /// The old space is kept if the new one can't be built
+    pub fn exec(&self, elf_data: &[u8], args: Vec<String>, envs: Vec<String>) -> Result<(), SysError> {
+        let (mut memory_set, user_sp, entry_point) = MemorySet::from_elf(elf_data)?;
+        let heap_bottom = user_sp;
+        let (user_sp, argv, envp) = init_user_stack(&mut memory_set, user_sp, &args, &envs)?;
         let trap_cx_ppn = memory_set
             .translate(VirtAddr::from(TRAP_CONTEXT).into())
             .unwrap()
@@ -102,43 +229,62 @@ impl TaskControlBlock {
         let mut inner = self.inner_exclusive_access();
         inner.memory_set = memory_set;
         inner.trap_cx_ppn = trap_cx_ppn;
-        inner.base_size = user_sp;
+        inner.base_size = heap_bottom;
+        inner.heap_bottom = heap_bottom;
+        inner.program_brk = heap_bottom;
+        inner.signal_actions.reset_handlers();
 
         let trap_cx = inner.get_trap_cx();
         *trap_cx = TrapContext::app_init_context(
             entry_point, 
             user_sp, 
-            KERNEL_SPACE.exclusive_access().token(), 
+            KERNEL_SPACE.lock().token(), 
             self.kernel_stack.get_top(), 
             trap_handler as usize
         );
+        // main(argc, argv, envp), they are also on the stack for the ones that look there
+        trap_cx.x[10] = args.len();
+        trap_cx.x[11] = argv;
+        trap_cx.x[12] = envp;
+        Ok(())
     }
 
-    pub fn fork(self: &Arc<Self>) -> Arc<Self> {
+    /// return None if there is not enough memory for the child
+    pub fn fork(self: &Arc<Self>) -> Option<Arc<Self>> {
         // access parent PCB exclusively
         let mut parent_inner = self.inner_exclusive_access();
-        // copy user space
-        let memory_set = MemorySet::from_existed_user(&parent_inner.memory_set);
+        // share user space with parent (copy-on-write)
+        let memory_set = MemorySet::from_existed_user(&mut parent_inner.memory_set)?;
         let trap_cx_ppn = memory_set.translate(VirtAddr::from(TRAP_CONTEXT).into()).unwrap().ppn();
         // allocate a pid and a kernel stack in kernel space
         let pid_handle = pid_alloc();
-        let kernel_stack = KernelStack::new(&pid_handle);
+        let kernel_stack = KernelStack::new(&pid_handle)?;
         let kernel_stack_top = kernel_stack.get_top();
         let task_control_block = Arc::new(TaskControlBlock {
             pid: pid_handle,
             kernel_stack,
-            inner: unsafe {
-                UPSafeCell::new(TaskControlBlockInner {
-                    trap_cx_ppn,
-                    base_size: parent_inner.base_size,
-                    task_cx: TaskContext::goto_trap_return(kernel_stack_top),
-                    task_status: TaskStatus::Ready,
-                    memory_set,
-                    parent: Some(Arc::downgrade(self)),
-                    children: Vec::new(),
-                    exit_code: 0,
-                })
-            },
+            inner: SpinNoIrqLock::new("TCB", TaskControlBlockInner {
+                trap_cx_ppn,
+                base_size: parent_inner.base_size,
+                task_cx: TaskContext::goto_trap_return(kernel_stack_top),
+                task_status: TaskStatus::Ready,
+                on_cpu: false,
+                sched_info: parent_inner.sched_info.fork(),
+                memory_set,
+                parent: Some(Arc::downgrade(self)),
+                children: Vec::new(),
+                // the child inherits all the opened files
+                fd_table: parent_inner.fd_table.clone(),
+                wait_queue: WaitQueue::new(),
+                exit_code: 0,
+                heap_bottom: parent_inner.heap_bottom,
+                program_brk: parent_inner.program_brk,
+                trace: parent_inner.trace,
+                // the pending signals are not inherited
+                signals: SignalFlags::empty(),
+                signal_mask: parent_inner.signal_mask,
+                signal_actions: parent_inner.signal_actions.clone(),
+            }),
         });
         // add child
         parent_inner.children.push(task_control_block.clone());
@@ -146,10 +292,37 @@ impl TaskControlBlock {
         let trap_cx = task_control_block.inner_exclusive_access().get_trap_cx();
         trap_cx.kernel_sp = kernel_stack_top;
         // return
-        task_control_block
+        Some(task_control_block)
     }
 
     pub fn getpid(&self) -> usize {
         self.pid.0
     }
+
+    /// change the location of the program break, return None if failed.
+    /// The heap can't grow into the mmap window
+    pub fn change_program_brk(&self, size: i32) -> Option<usize> {
+        let mut inner = self.inner_exclusive_access();
+        let heap_bottom = inner.heap_bottom;
+        let old_brk = inner.program_brk;
+        let new_brk = old_brk as isize + size as isize;
+        if new_brk < heap_bottom as isize || new_brk as usize > MMAP_BASE {
+            return None;
+        }
+        let result = if size < 0 {
+            inner
+                .memory_set
+                .shrink_to(VirtAddr(heap_bottom), VirtAddr(new_brk as usize))
+        } else {
+            inner
+                .memory_set
+                .append_to(VirtAddr(heap_bottom), VirtAddr(new_brk as usize))
+        };
+        if result {
+            inner.program_brk = new_brk as usize;
+            Some(old_brk)
+        } else {
+            None
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,33 @@
+use alloc::{collections::vec_deque::VecDeque, sync::Arc};
+
+use super::task::TaskControlBlock;
+
+/// Tasks blocked until some event happens, they are woken up by whoever triggers the event
+pub struct WaitQueue {
+    queue: VecDeque<Arc<TaskControlBlock>>,
+}
+
+impl WaitQueue {
+    pub fn new() -> Self {
+        Self {
+            queue: VecDeque::new(),
+        }
+    }
+
+    /// Add a task which is going to block, a task woken up spuriously is only kept once
+    pub fn push(&mut self, task: Arc<TaskControlBlock>) {
+        if !self.queue.iter().any(|waiter| Arc::ptr_eq(waiter, &task)) {
+            self.queue.push_back(task);
+        }
+    }
+
+    /// Remove a task whose wait is interrupted by a signal, so the queue holds no stale entry
+    pub fn remove(&mut self, task: &Arc<TaskControlBlock>) {
+        self.queue.retain(|waiter| !Arc::ptr_eq(waiter, task));
+    }
+
+    /// Remove all the waiting tasks, wake them up after releasing the borrow of the queue owner
+    pub fn take_all(&mut self) -> VecDeque<Arc<TaskControlBlock>> {
+        core::mem::take(&mut self.queue)
+    }
+}
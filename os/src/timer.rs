@@ -1,9 +1,14 @@
+use core::cmp::Ordering;
+
+use alloc::{collections::binary_heap::BinaryHeap, sync::Arc};
+use lazy_static::lazy_static;
 use riscv::register::time;
 
-use crate::{config::CLOCK_FREQ, sbi::set_timer};
+use crate::{board::clock_freq, sbi::set_timer, sync::SpinNoIrqLock, task::{wakeup_task, TaskControlBlock}};
 
 const TICKS_PER_SEC: usize = 100; // tick 100 times in 1s
 const MSEC_PER_SEC: usize = 1000;
+pub const NSEC_PER_SEC: usize = 1_000_000_000;
 
 /// read the mtime register
 pub fn get_time() -> usize {
@@ -12,10 +17,83 @@ pub fn get_time() -> usize {
 
 /// get current time in milliseconds
 pub fn get_time_ms() -> usize {
-    time::read() / (CLOCK_FREQ / MSEC_PER_SEC)
+    time::read() / (clock_freq() / MSEC_PER_SEC)
 }
 
 /// set the next timer interrupt
 pub fn set_next_trigger() {
-    set_timer(get_time() + CLOCK_FREQ / TICKS_PER_SEC);
-}
\ No newline at end of file
+    set_timer(get_time() + clock_freq() / TICKS_PER_SEC);
+}
+
+/// the same layout as `struct timespec` in Linux
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct TimeSpec {
+    pub sec: usize,
+    pub nsec: usize,
+}
+
+impl TimeSpec {
+    /// convert to mtime ticks, saturating instead of overflowing
+    pub fn to_ticks(&self) -> usize {
+        self.sec
+            .saturating_mul(clock_freq())
+            .saturating_add(self.nsec * (clock_freq() / 100) / (NSEC_PER_SEC / 100))
+    }
+}
+
+/// a task sleeping until `expire` (in mtime ticks)
+pub struct TimerCondVar {
+    pub expire: usize,
+    pub task: Arc<TaskControlBlock>,
+}
+
+impl PartialEq for TimerCondVar {
+    fn eq(&self, other: &Self) -> bool {
+        self.expire == other.expire
+    }
+}
+
+impl Eq for TimerCondVar {}
+
+impl PartialOrd for TimerCondVar {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TimerCondVar {
+    /// reversed, so that the BinaryHeap pops the earliest deadline first
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.expire.cmp(&self.expire)
+    }
+}
+
+lazy_static! {
+    /// sleeping tasks ordered by their wake-up tick
+    static ref TIMERS: SpinNoIrqLock<BinaryHeap<TimerCondVar>> =
+        SpinNoIrqLock::new("TIMERS", BinaryHeap::new());
+}
+
+/// wake up `task` once mtime reaches `expire`, the task should block itself afterwards
+pub fn add_timer(expire: usize, task: Arc<TaskControlBlock>) {
+    TIMERS.lock().push(TimerCondVar { expire, task });
+}
+
+/// remove the timers of `task`, whose sleep is interrupted by a signal
+pub fn cancel_timer(task: &Arc<TaskControlBlock>) {
+    TIMERS.lock().retain(|timer| !Arc::ptr_eq(&timer.task, task));
+}
+
+/// wake up all the tasks whose deadline has passed
+pub fn check_timer() {
+    let current = get_time();
+    let mut timers = TIMERS.lock();
+    while let Some(timer) = timers.peek() {
+        if timer.expire > current {
+            break;
+        }
+        let timer = timers.pop().unwrap();
+        wakeup_task(timer.task);
+    }
+}
@@ -15,6 +15,8 @@ pub struct TrapContext {
     pub kernel_sp: usize,
     /// Addr of trap_handler function
     pub trap_handler: usize,
+    /// kernel tp, the id of the hart the task last trapped on
+    pub kernel_tp: usize,
 }
 
 impl TrapContext {
@@ -33,6 +35,7 @@ impl TrapContext {
             kernel_satp,
             kernel_sp,
             trap_handler,
+            kernel_tp: 0,
         };
         cx.set_sp(sp);
         cx
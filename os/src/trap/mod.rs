@@ -5,21 +5,24 @@ pub use context::TrapContext;
 use riscv::register::{
     mtvec::TrapMode,
     scause::{self, Exception, Interrupt, Trap},
-    sie, stval, stvec,
+    sepc, sie, sstatus, stval, stvec,
 };
 
-use crate::{config::{TRAMPOLINE, TRAP_CONTEXT}, println, syscall::syscall, task::{current_trap_cx, current_user_token, exit_current_and_run_next, suspend_current_and_run_next}, timer::set_next_trigger};
+use crate::{config::{TRAMPOLINE, TRAP_CONTEXT}, drivers, mm::Access, println, syscall::syscall, task::{current_task, current_trap_cx, current_user_token, force_current_signal, handle_signals, preempt_current_and_run_next, TaskStatus, SIGILL, SIGSEGV}, timer::{check_timer, set_next_trigger}};
 
 global_asm!(include_str!("trap.S"));
 
-/// initialize CSR stvec as the entry of __alltraps
+/// initialize CSR stvec as the entry of the traps taken in kernel
 pub fn init() {
     set_kernel_trap_entry();
 }
 
 fn set_kernel_trap_entry() {
+    unsafe extern "C" {
+        fn __kernel_trap();
+    }
     unsafe {
-        stvec::write(trap_from_kernel as usize, TrapMode::Direct);
+        stvec::write(__kernel_trap as usize, TrapMode::Direct);
     }
 }
 
@@ -36,6 +39,15 @@ pub fn enable_timer_interrupt() {
     }
 }
 
+/// wait for an interrupt when there is nothing to run, it's handled by `kernel_trap_handler`
+pub fn wait_for_interrupt() {
+    unsafe {
+        sstatus::set_sie();
+        asm!("wfi");
+        sstatus::clear_sie();
+    }
+}
+
 /// handle interrupt, exception, system call from user space
 #[unsafe(no_mangle)]
 pub fn trap_handler() -> ! {
@@ -48,12 +60,24 @@ pub fn trap_handler() -> ! {
             // jump to next instruction anyway
             let mut cx = current_trap_cx();
             cx.sepc += 4; // cuz it points to ecall originially
-            // get system call return value
+            // get system call return value, a long syscall may be preempted
+            unsafe {
+                sstatus::set_sie();
+            }
             let result = syscall(cx.x[17], [cx.x[10], cx.x[11], cx.x[12]]);
+            unsafe {
+                sstatus::clear_sie();
+            }
             // cx is changed if during sys_exec, so we have to call it again
             cx = current_trap_cx();
             cx.x[10] = result as usize;
         },
+        Trap::Exception(
+            e @ (Exception::StorePageFault | Exception::LoadPageFault | Exception::InstructionPageFault),
+        ) if handle_user_page_fault(e, stval) => {
+            // demand paging or copy-on-write, simply return to the faulting instruction.
+            // Running out of frames for it raises SIGSEGV below as well
+        },
         Trap::Exception(Exception::StoreFault)
         | Trap::Exception(Exception::StorePageFault)
         | Trap::Exception(Exception::InstructionFault)
@@ -61,25 +85,24 @@ pub fn trap_handler() -> ! {
         | Trap::Exception(Exception::LoadFault)
         | Trap::Exception(Exception::LoadPageFault) => {
             println!(
-                "[kernel] {:?} in application, bad addr = {:#?}, bad instruction = {:#x}, kernel killed it.",
+                "[kernel] {:?} in application, bad addr = {:#?}, bad instruction = {:#x}, raise SIGSEGV.",
                 scause.cause(),
                 stval,
                 current_trap_cx().sepc,
             );
-            exit_current_and_run_next(-2);
-            // println!("[kernel] PageFault in application, bad addr = {:#x}, bad instruction = {:#x}, kernel killed it.", stval, cx.sepc);
-            // exit_current_and_run_next();
+            force_current_signal(SIGSEGV);
         },
         Trap::Exception(Exception::IllegalInstruction) => {
-            println!("[kernel] IllegalInstruction in application, kernel killed it.");
-            exit_current_and_run_next(-3);
-            // exit_current_and_run_next();
-            // panic!("[kernel] Cannot continue!");
-            // run_next_app();
+            println!("[kernel] IllegalInstruction in application, raise SIGILL.");
+            force_current_signal(SIGILL);
         },
         Trap::Interrupt(Interrupt::SupervisorTimer) => {
             set_next_trigger();
-            suspend_current_and_run_next();
+            check_timer();
+            preempt_current_and_run_next();
+        },
+        Trap::Interrupt(Interrupt::SupervisorExternal) => {
+            drivers::handle_irq();
         },
         _ => {
             panic!("Unsupported trap {:?}, stval = {:#x}!", scause.cause(), stval);
@@ -88,12 +111,32 @@ pub fn trap_handler() -> ! {
     trap_return()
 }
 
+/// try to fix a page fault of the current task at `stval`, return false if it's a real fault
+fn handle_user_page_fault(e: Exception, stval: usize) -> bool {
+    let access = match e {
+        Exception::StorePageFault => Access::Write,
+        Exception::InstructionPageFault => Access::Execute,
+        _ => Access::Read,
+    };
+    current_task()
+        .unwrap()
+        .inner_exclusive_access()
+        .memory_set
+        .handle_page_fault(stval.into(), access)
+        .is_ok()
+}
 
 #[unsafe(no_mangle)]
 /// set the new addr of __restore asm function in TRAMPOLINE page,
 /// set the reg a0 = trap_cx_ptr, reg a1 = phy addr of usr page table,
 /// finally, jump to new addr of __restore asm function
 pub fn trap_return() -> ! {
+    // no interrupt may be taken in kernel with the user trap entry
+    unsafe {
+        sstatus::clear_sie();
+    }
+    // it may enter a signal handler instead, or never return if the task is killed
+    handle_signals();
     set_user_trap_entry();
     let trap_cx_ptr = TRAP_CONTEXT;
     let user_satp = current_user_token();
@@ -115,8 +158,35 @@ pub fn trap_return() -> ! {
 }
 
 
+/// handle the interrupts taken in kernel, they are only enabled in syscalls and the idle loop.
+/// The kernel reads user memory through the physical addresses, so it never page faults on a user
+/// copy, a bad user pointer is reported as EFAULT by the translation instead
 #[unsafe(no_mangle)]
-pub fn trap_from_kernel() -> ! {
-    panic!("a trap {:?} from kernel!", scause::read().cause());
-    // panic!("a trap from kernel!");
+pub fn kernel_trap_handler() {
+    let scause = scause::read();
+    match scause.cause() {
+        Trap::Interrupt(Interrupt::SupervisorTimer) => {
+            set_next_trigger();
+            check_timer();
+            // the interrupts are disabled while holding a lock, so it's safe to switch here.
+            // A task that is about to block or exit switches out by itself
+            let running = current_task().is_some_and(|task| {
+                task.inner_exclusive_access().task_status == TaskStatus::Running
+            });
+            if running {
+                preempt_current_and_run_next();
+            }
+        }
+        Trap::Interrupt(Interrupt::SupervisorExternal) => {
+            drivers::handle_irq();
+        }
+        _ => {
+            panic!(
+                "a trap {:?} from kernel, stval = {:#x}, sepc = {:#x}!",
+                scause.cause(),
+                stval::read(),
+                sepc::read(),
+            );
+        }
+    }
 }
@@ -0,0 +1,69 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::arch::asm;
+
+use user_lib::{exit, fork, waitpid};
+
+const SYSCALL_READ: usize = 63;
+const SYSCALL_WRITE: usize = 64;
+const SYSCALL_EXEC: usize = 221;
+const SYSCALL_WAITPID: usize = 260;
+
+const EFAULT: isize = -14;
+
+/// user_lib only accepts valid references, so issue the bad calls by hand
+fn raw_syscall(id: usize, args: [usize; 3]) -> isize {
+    let mut ret;
+    unsafe {
+        asm!(
+            "ecall",
+            inlateout("x10") args[0] => ret,
+            in("x11") args[1],
+            in("x12") args[2],
+            in("x17") id
+        );
+    }
+    ret
+}
+
+static READ_ONLY: [u8; 4] = *b"ro\n\0";
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    // unmapped, kernel space, not accessible from U-mode and wrapping around
+    assert_eq!(raw_syscall(SYSCALL_WRITE, [1, 0, 16]), EFAULT);
+    assert_eq!(raw_syscall(SYSCALL_WRITE, [1, 0x8020_0000, 16]), EFAULT);
+    assert_eq!(raw_syscall(SYSCALL_WRITE, [1, usize::MAX - 0xfff, 16]), EFAULT);
+    assert_eq!(raw_syscall(SYSCALL_WRITE, [1, usize::MAX - 4, 16]), EFAULT);
+    // a valid start running into an unmapped page
+    let ro = READ_ONLY.as_ptr() as usize;
+    assert_eq!(raw_syscall(SYSCALL_WRITE, [1, ro, 0x100_0000]), EFAULT);
+    println!("bad sys_write buffers rejected");
+
+    // readable but not writable
+    assert_eq!(raw_syscall(SYSCALL_READ, [0, ro, 1]), EFAULT);
+    assert_eq!(raw_syscall(SYSCALL_READ, [0, 0, 1]), EFAULT);
+    println!("bad sys_read buffers rejected");
+
+    assert_eq!(raw_syscall(SYSCALL_EXEC, [0, 0, 0]), EFAULT);
+    assert_eq!(raw_syscall(SYSCALL_EXEC, [0x8020_0000, 0, 0]), EFAULT);
+    println!("bad sys_exec path rejected");
+
+    let pid = fork().unwrap();
+    if pid == 0 {
+        exit(7);
+    }
+    // the child is kept until it's waited with a good pointer
+    assert_eq!(raw_syscall(SYSCALL_WAITPID, [pid, ro, 0]), EFAULT);
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), Ok(pid));
+    assert_eq!(exit_code, 7);
+    println!("bad sys_waitpid pointer rejected");
+
+    println!("bad address test passed!");
+    0
+}
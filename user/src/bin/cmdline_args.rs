@@ -0,0 +1,33 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+#[macro_use]
+extern crate user_lib;
+
+use alloc::{string::String, vec};
+use user_lib::{exec, SysError};
+
+#[unsafe(no_mangle)]
+pub fn main(argc: usize, argv: &[&str]) -> i32 {
+    println!("argc = {}", argc);
+    for (i, arg) in argv.iter().enumerate() {
+        println!("argv[{}] = {}", i, arg);
+    }
+    assert_eq!(argc, 4);
+    assert_eq!(argv, ["cmdline_args", "aaa", "bbb", "ccc"]);
+
+    // the arguments have to fit in the new user stack
+    let mut huge = String::from("cmdline_args\0");
+    huge.extend(core::iter::repeat_n('x', 4096));
+    huge.push('\0');
+    let args = [huge.as_ptr(), core::ptr::null()];
+    assert_eq!(exec("cmdline_args\0", &args, &[core::ptr::null()]), Err(SysError::E2BIG));
+    // so do the pointers, the same short string may be passed many times
+    let mut args = vec!["x\0".as_ptr(); 512];
+    args.push(core::ptr::null());
+    assert_eq!(exec("cmdline_args\0", &args, &[core::ptr::null()]), Err(SysError::E2BIG));
+    println!("cmdline args test passed!");
+    0
+}
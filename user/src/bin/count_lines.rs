@@ -0,0 +1,27 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{read, STDIN};
+
+/// count the lines read from stdin until EOF, try `hello_world | count_lines` in the shell
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    let mut lines = 0;
+    let mut last = b'\n';
+    let mut c = [0u8; 1];
+    while read(STDIN, &mut c) == Ok(1) {
+        if c[0] == b'\n' {
+            lines += 1;
+        }
+        last = c[0];
+    }
+    // the last line may not end with '\n'
+    if last != b'\n' {
+        lines += 1;
+    }
+    println!("{}", lines);
+    0
+}
@@ -0,0 +1,48 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, munmap, set_log_level, syslog_clear, syslog_read, syslog_size, write, PROT_READ, PROT_WRITE, STDOUT};
+
+const PAGE_SIZE: usize = 0x1000;
+
+/// print the kernel log, `-c` clears it afterwards and `-n LEVEL` sets the max level (0 to 5)
+#[unsafe(no_mangle)]
+pub fn main(argc: usize, argv: &[&str]) -> i32 {
+    match argv.get(1).copied() {
+        Some("-n") if argc == 3 => {
+            let Ok(level) = argv[2].parse() else {
+                println!("dmesg: invalid level {}", argv[2]);
+                return -1;
+            };
+            return match set_log_level(level) {
+                Ok(_) => 0,
+                Err(err) => {
+                    println!("dmesg: {:?}", err);
+                    -1
+                }
+            };
+        }
+        None | Some("-c") if argc <= 2 => {}
+        _ => {
+            println!("usage: dmesg [-c | -n LEVEL]");
+            return -1;
+        }
+    }
+    let size = syslog_size().unwrap();
+    if size > 0 {
+        // the log may be larger than the user heap
+        let len = size.div_ceil(PAGE_SIZE) * PAGE_SIZE;
+        let addr = mmap(0, len, PROT_READ | PROT_WRITE).unwrap();
+        let buffer = unsafe { core::slice::from_raw_parts_mut(addr as *mut u8, len) };
+        let n = syslog_read(buffer).unwrap();
+        write(STDOUT, &buffer[..n]).unwrap();
+        munmap(addr, len).unwrap();
+    }
+    if argc == 2 {
+        syslog_clear().unwrap();
+    }
+    0
+}
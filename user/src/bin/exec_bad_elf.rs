@@ -0,0 +1,55 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, exec, open, write, SysError, O_CREAT, O_TRUNC, O_WRONLY};
+
+const EHDR_SIZE: usize = 64;
+const PHDR_SIZE: usize = 56;
+
+/// an ELF64 header for RISC-V followed by `N` PT_LOAD segments of one read-only page each
+fn elf<const N: usize>(vaddrs: [u64; N]) -> [u8; 256] {
+    let mut image = [0u8; 256];
+    image[..8].copy_from_slice(&[0x7f, b'E', b'L', b'F', 2, 1, 1, 0]);
+    image[16..18].copy_from_slice(&2u16.to_le_bytes()); // ET_EXEC
+    image[18..20].copy_from_slice(&0xf3u16.to_le_bytes()); // EM_RISCV
+    image[20..24].copy_from_slice(&1u32.to_le_bytes());
+    image[24..32].copy_from_slice(&vaddrs[0].to_le_bytes());
+    image[32..40].copy_from_slice(&(EHDR_SIZE as u64).to_le_bytes());
+    image[52..54].copy_from_slice(&(EHDR_SIZE as u16).to_le_bytes());
+    image[54..56].copy_from_slice(&(PHDR_SIZE as u16).to_le_bytes());
+    image[56..58].copy_from_slice(&(N as u16).to_le_bytes());
+    for (i, vaddr) in vaddrs.iter().enumerate() {
+        let ph = &mut image[EHDR_SIZE + i * PHDR_SIZE..EHDR_SIZE + (i + 1) * PHDR_SIZE];
+        ph[..4].copy_from_slice(&1u32.to_le_bytes()); // PT_LOAD
+        ph[4..8].copy_from_slice(&4u32.to_le_bytes()); // PF_R
+        ph[16..24].copy_from_slice(&vaddr.to_le_bytes());
+        ph[24..32].copy_from_slice(&vaddr.to_le_bytes());
+        ph[40..48].copy_from_slice(&0x1000u64.to_le_bytes());
+        ph[48..56].copy_from_slice(&0x1000u64.to_le_bytes());
+    }
+    image
+}
+
+/// write `data` to the file `name` and exec it
+fn exec_file(name: &str, data: &[u8]) -> Result<usize, SysError> {
+    let fd = open(name, O_CREAT | O_WRONLY | O_TRUNC).unwrap();
+    assert_eq!(write(fd, data), Ok(data.len()));
+    close(fd).unwrap();
+    exec(name, &[name.as_ptr(), core::ptr::null()], &[core::ptr::null()])
+}
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    assert_eq!(exec_file("bad_elf\0", b"#!/bin/sh\n"), Err(SysError::ENOEXEC));
+    // the segments can't overlap each other
+    assert_eq!(exec_file("bad_elf\0", &elf([0x10000, 0x10000])), Err(SysError::ENOEXEC));
+    // nor run into the mmap window or TrapContext
+    assert_eq!(exec_file("bad_elf\0", &elf([0x10_0000_0000])), Err(SysError::ENOEXEC));
+    assert_eq!(exec_file("bad_elf\0", &elf([0xffff_ffff_ffff_e000])), Err(SysError::ENOEXEC));
+    // a failed exec leaves the old program running
+    println!("exec_bad_elf passed!");
+    0
+}
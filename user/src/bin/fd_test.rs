@@ -0,0 +1,46 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, dup, dup2, exit, fork, read, waitpid, write, SysError, STDERR, STDIN, STDOUT};
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    // stdin, stdout and stderr are opened, so a new fd starts from 3
+    let fd = dup(STDOUT).unwrap();
+    assert_eq!(fd, 3);
+    assert_eq!(write(fd, b"write through a dup of stdout\n"), Ok(30));
+    assert_eq!(write(STDERR, b"write to stderr\n"), Ok(16));
+    // wrong direction
+    assert_eq!(read(STDOUT, &mut [0u8]), Err(SysError::EBADF));
+    assert_eq!(write(STDIN, b"x"), Err(SysError::EBADF));
+
+    assert_eq!(close(fd), Ok(0));
+    assert_eq!(close(fd), Err(SysError::EBADF));
+    assert_eq!(write(fd, b"x"), Err(SysError::EBADF));
+    assert_eq!(dup(42), Err(SysError::EBADF));
+    println!("dup and close ok");
+
+    // dup2 to a far fd, then the lowest free fd is reused
+    assert_eq!(dup2(STDOUT, 10), Ok(10));
+    assert_eq!(dup2(10, 10), Ok(10));
+    assert_eq!(dup2(11, 11), Err(SysError::EBADF));
+    assert_eq!(dup(STDOUT), Ok(3));
+    assert_eq!(write(10, b"write through fd 10\n"), Ok(20));
+
+    // the child inherits the fd table, closing a fd there doesn't affect the parent
+    let pid = fork().unwrap();
+    if pid == 0 {
+        assert_eq!(write(10, b"child writes to fd 10\n"), Ok(22));
+        close(10).unwrap();
+        exit(0);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), Ok(pid));
+    assert_eq!(exit_code, 0);
+    assert_eq!(write(10, b"parent still has fd 10\n"), Ok(23));
+    println!("fd test passed!");
+    0
+}
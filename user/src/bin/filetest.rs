@@ -0,0 +1,60 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::{vec, vec::Vec};
+use user_lib::{close, open, read, write, SysError, O_CREAT, O_RDONLY, O_RDWR, O_TRUNC, O_WRONLY};
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    let name = "filea\0";
+    let text = b"Hello, easy-fs!";
+
+    // create (or truncate the file left by the last run) and write
+    let fd = open(name, O_CREAT | O_WRONLY | O_TRUNC).unwrap();
+    assert_eq!(write(fd, text), Ok(text.len()));
+    assert_eq!(read(fd, &mut [0u8; 4]), Err(SysError::EBADF));
+    close(fd).unwrap();
+
+    // read it back, the offset moves on
+    let fd = open(name, O_RDONLY).unwrap();
+    let mut buf = [0u8; 32];
+    assert_eq!(read(fd, &mut buf[..5]), Ok(5));
+    assert_eq!(read(fd, &mut buf[5..]), Ok(text.len() - 5));
+    assert_eq!(&buf[..text.len()], text);
+    assert_eq!(read(fd, &mut buf), Ok(0));
+    assert_eq!(write(fd, text), Err(SysError::EBADF));
+    close(fd).unwrap();
+    println!("write and read back ok");
+
+    // a file spanning several blocks, overwritten from the start, on the heap as the user stack is a page
+    let fd = open(name, O_RDWR).unwrap();
+    let big: Vec<u8> = (0..2000).map(|i| (i % 251) as u8).collect();
+    assert_eq!(write(fd, &big), Ok(big.len()));
+    close(fd).unwrap();
+    let fd = open(name, O_RDONLY).unwrap();
+    let mut out = vec![0u8; 2048];
+    assert_eq!(read(fd, &mut out), Ok(big.len()));
+    assert_eq!(&out[..big.len()], &big[..]);
+    close(fd).unwrap();
+    println!("multi-block file ok");
+
+    // O_TRUNC empties it
+    let fd = open(name, O_WRONLY | O_TRUNC).unwrap();
+    close(fd).unwrap();
+    let fd = open(name, O_RDONLY).unwrap();
+    assert_eq!(read(fd, &mut buf), Ok(0));
+    close(fd).unwrap();
+
+    assert_eq!(open("no_such_file\0", O_RDONLY), Err(SysError::ENOENT));
+    assert_eq!(open("dir/filea\0", O_RDONLY | O_CREAT), Err(SysError::ENOENT));
+    assert_eq!(
+        open("a_file_name_longer_than_27_bytes\0", O_CREAT | O_RDWR),
+        Err(SysError::ENAMETOOLONG)
+    );
+    println!("filetest passed!");
+    0
+}
@@ -8,25 +8,29 @@ use user_lib::{exec, fork, getpid, wait};
 
 #[unsafe(no_mangle)]
 pub fn main() -> i32 {
-    println!("pid {}: parent start forking ...", getpid());
-    let pid = fork();
+    println!("pid {}: parent start forking ...", getpid().unwrap());
+    let pid = fork().unwrap();
     if pid == 0 {
         // child process
         println!(
             "pid {}: forked child start execing hello_world app ... ",
-            getpid()
+            getpid().unwrap()
+        );
+        let _ = exec(
+            "hello_world\0",
+            &["hello_world\0".as_ptr(), core::ptr::null()],
+            &[core::ptr::null()],
         );
-        exec("hello_world\0");
         100
     } else {
         // parent process
         let mut exit_code: i32 = 0;
-        println!("pid {}: ready waiting child ...", getpid());
-        assert_eq!(pid, wait(&mut exit_code));
+        println!("pid {}: ready waiting child ...", getpid().unwrap());
+        assert_eq!(wait(&mut exit_code), Ok(pid));
         assert_eq!(exit_code, 0);
         println!(
             "pid {}: got child info:: pid {}, exit code: {}",
-            getpid(),
+            getpid().unwrap(),
             pid,
             exit_code
         );
@@ -11,24 +11,24 @@ static NUM: usize = 30;
 #[unsafe(no_mangle)]
 pub fn main() -> i32 {
     for _ in 0..NUM {
-        let pid = fork();
+        let pid = fork().unwrap();
         if pid == 0 {
-            let current_time = get_time();
+            let current_time = get_time().unwrap();
             let sleep_length =
                 (current_time as i32 as isize) * (current_time as i32 as isize) % 1000 + 1000;
-            println!("pid {} sleep for {} ms", getpid(), sleep_length);
+            println!("pid {} sleep for {} ms", getpid().unwrap(), sleep_length);
             sleep(sleep_length as usize);
-            println!("pid {} OK!", getpid());
+            println!("pid {} OK!", getpid().unwrap());
             exit(0);
         }
     }
 
     let mut exit_code: i32 = 0;
     for _ in 0..NUM {
-        assert!(wait(&mut exit_code) > 0);
+        assert!(wait(&mut exit_code).is_ok());
         assert_eq!(exit_code, 0);
     }
-    assert!(wait(&mut exit_code) < 0);
+    assert!(wait(&mut exit_code).is_err());
     println!("forktest2 test passed!");
     0
 }
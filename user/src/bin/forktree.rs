@@ -16,7 +16,7 @@ fn fork_child(cur: &str, branch: char) {
     }
     next[..l].copy_from_slice(cur.as_bytes());
     next[l] = branch as u8;
-    if fork() == 0 {
+    if fork().unwrap() == 0 {
         fork_tree(core::str::from_utf8(&next[..l + 1]).unwrap());
         yield_();
         exit(0);
@@ -24,7 +24,7 @@ fn fork_child(cur: &str, branch: char) {
 }
 
 fn fork_tree(cur: &str) {
-    println!("pid{}: {}", getpid(), cur);
+    println!("pid{}: {}", getpid().unwrap(), cur);
     fork_child(cur, '0');
     fork_child(cur, '1');
 }
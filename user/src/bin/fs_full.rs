@@ -0,0 +1,56 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, open, write, SysError, O_CREAT, O_TRUNC, O_WRONLY};
+
+/// the blocks an inode maps: 28 direct, 128 indirect and 128 * 128 doubly indirect
+const MAX_FILE_SIZE: usize = (28 + 128 + 128 * 128) * 512;
+
+static CHUNK: [u8; 8192] = [0x5a; 8192];
+
+/// write to `name` until it fails, return the size and the error
+fn fill(name: &str) -> (usize, SysError) {
+    let fd = open(name, O_CREAT | O_WRONLY | O_TRUNC).unwrap();
+    let mut size = 0;
+    let err = loop {
+        match write(fd, &CHUNK) {
+            Ok(len) => size += len,
+            Err(err) => break err,
+        }
+    };
+    close(fd).unwrap();
+    (size, err)
+}
+
+/// truncate the file to give its blocks back
+fn empty(name: &str) {
+    close(open(name, O_WRONLY | O_TRUNC).unwrap()).unwrap();
+}
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    // the image is 16 MiB, a file stops growing at the largest size first
+    let (size, err) = fill("full_a\0");
+    assert_eq!(err, SysError::EFBIG);
+    assert!(size <= MAX_FILE_SIZE && size + CHUNK.len() > MAX_FILE_SIZE);
+    println!("file too large ok, {} bytes", size);
+
+    // then there are no blocks left for another one of that size
+    let (size, err) = fill("full_b\0");
+    assert_eq!(err, SysError::ENOSPC);
+    assert!(size < MAX_FILE_SIZE);
+    println!("disk full ok, {} bytes", size);
+
+    // the blocks of a truncated file can be used again
+    empty("full_a\0");
+    let fd = open("full_a\0", O_WRONLY).unwrap();
+    assert_eq!(write(fd, &CHUNK), Ok(CHUNK.len()));
+    close(fd).unwrap();
+    empty("full_a\0");
+    empty("full_b\0");
+    println!("fs_full passed!");
+    0
+}
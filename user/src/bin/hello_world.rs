@@ -8,6 +8,6 @@ use user_lib::getpid;
 
 #[unsafe(no_mangle)]
 pub fn main() -> i32 {
-    println!("pid {}: Hello world from user mode program!", getpid());
+    println!("pid {}: Hello world from user mode program!", getpid().unwrap());
     0
 }
@@ -8,18 +8,21 @@ use user_lib::{exec, fork, wait, yield_};
 
 #[unsafe(no_mangle)]
 fn main() -> i32 {
-    if fork() == 0 {
+    if fork().unwrap() == 0 {
         // if the return value is 0, it means it's in the sub-process (the parent process will get the sub-pid value)
-        exec("user_shell\0");
+        let _ = exec(
+            "user_shell\0",
+            &["user_shell\0".as_ptr(), core::ptr::null()],
+            &[core::ptr::null()],
+        );
     } else {
         loop {
             let mut exit_code: i32 = 0;
-            // the exit_code stores the returning value from sub-process
-            let pid = wait(&mut exit_code);
-            if pid == -1 {
+            // blocks until a child or an adopted orphan exits, exit_code stores its returning value
+            let Ok(pid) = wait(&mut exit_code) else {
                 yield_();
                 continue;
-            }
+            };
             println!(
                 "[initproc] Relesed a zombie process, pid={}, exit_code={}",
                 pid, exit_code,
@@ -23,7 +23,7 @@ fn work(times: isize) {
         }
     }
     yield_();
-    println!("pid {} is running ({} times)!.", getpid(), times);
+    println!("pid {} is running ({} times)!.", getpid().unwrap(), times);
     for _ in 0..times {
         for i in 0..N {
             for j in 0..N {
@@ -41,16 +41,16 @@ fn work(times: isize) {
             }
         }
     }
-    println!("pid {} done!.", getpid());
+    println!("pid {} done!.", getpid().unwrap());
     exit(0);
 }
 
 #[unsafe(no_mangle)]
 pub fn main() -> i32 {
     for _ in 0..NUM {
-        let pid = fork();
+        let pid = fork().unwrap();
         if pid == 0 {
-            let current_time = get_time();
+            let current_time = get_time().unwrap();
             let times = (current_time as i32 as isize) * (current_time as i32 as isize) % 1000;
             work(times * 10);
         }
@@ -60,11 +60,11 @@ pub fn main() -> i32 {
 
     let mut exit_code: i32 = 0;
     for _ in 0..NUM {
-        if wait(&mut exit_code) < 0 {
+        if wait(&mut exit_code).is_err() {
             panic!("wait failed.");
         }
     }
-    assert!(wait(&mut exit_code) < 0);
+    assert!(wait(&mut exit_code).is_err());
     println!("matrix passed.");
     0
 }
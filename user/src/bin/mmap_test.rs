@@ -0,0 +1,89 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, mmap, mprotect, munmap, waitpid, SysError, PROT_EXEC, PROT_READ, PROT_WRITE, SIGILL, SIGSEGV};
+
+const PAGE_SIZE: usize = 0x1000;
+const MMAP_BASE: usize = 0x10_0000_0000;
+
+/// run `f(arg)` in a child process and return its exit code
+fn in_child(f: fn(usize), arg: usize) -> i32 {
+    let pid = fork().unwrap();
+    if pid == 0 {
+        f(arg);
+        exit(0);
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), Ok(pid));
+    exit_code
+}
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    // let the kernel choose the address
+    let addr = mmap(0, PAGE_SIZE * 4, PROT_READ | PROT_WRITE).unwrap();
+    println!("mmap 4 pages at {:#x}", addr);
+    let area = unsafe { core::slice::from_raw_parts_mut(addr as *mut u8, PAGE_SIZE * 4) };
+    for (i, byte) in area.iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+    for (i, byte) in area.iter().enumerate() {
+        assert_eq!(*byte, i as u8);
+    }
+    println!("read and write ok");
+
+    // overlapping and misaligned requests are rejected
+    assert_eq!(mmap(addr, PAGE_SIZE, PROT_READ), Err(SysError::EINVAL));
+    assert_eq!(mmap(addr + PAGE_SIZE * 3, PAGE_SIZE * 2, PROT_READ), Err(SysError::EINVAL));
+    assert_eq!(mmap(MMAP_BASE + PAGE_SIZE * 64 + 1, PAGE_SIZE, PROT_READ), Err(SysError::EINVAL));
+    assert_eq!(mmap(MMAP_BASE + PAGE_SIZE * 64, 0, PROT_READ), Err(SysError::EINVAL));
+    assert_eq!(mmap(MMAP_BASE + PAGE_SIZE * 64, PAGE_SIZE, 0), Err(SysError::EINVAL));
+    assert_eq!(munmap(addr + 1, PAGE_SIZE), Err(SysError::EINVAL));
+    assert_eq!(munmap(addr + PAGE_SIZE * 3, PAGE_SIZE * 2), Err(SysError::EINVAL));
+    println!("bad requests rejected");
+
+    // a fixed address inside the mmap window
+    let fixed = MMAP_BASE + PAGE_SIZE * 64;
+    assert_eq!(mmap(fixed, PAGE_SIZE, PROT_READ | PROT_WRITE), Ok(fixed));
+    unsafe { (fixed as *mut usize).write_volatile(0xdead) };
+    assert_eq!(munmap(fixed, PAGE_SIZE), Ok(0));
+
+    // punch a hole in the middle, the rest of the area stays usable
+    assert_eq!(munmap(addr + PAGE_SIZE, PAGE_SIZE), Ok(0));
+    assert_eq!(area[0], 0);
+    assert_eq!(area[PAGE_SIZE * 2], 0);
+    assert_eq!(area[PAGE_SIZE * 4 - 1], (PAGE_SIZE * 4 - 1) as u8);
+    let code = in_child(|hole| unsafe {
+        (hole as *const u8).read_volatile();
+    }, addr + PAGE_SIZE);
+    assert_eq!(code, -(SIGSEGV as i32));
+    println!("partial munmap ok");
+
+    // a read-only page can be read but not written
+    assert_eq!(mprotect(addr + PAGE_SIZE * 2, PAGE_SIZE, PROT_READ), Ok(0));
+    assert_eq!(area[PAGE_SIZE * 2 + 1], 1);
+    let code = in_child(|read_only| unsafe {
+        (read_only as *mut u8).write_volatile(0);
+    }, addr + PAGE_SIZE * 2);
+    assert_eq!(code, -(SIGSEGV as i32));
+    assert_eq!(mprotect(addr + PAGE_SIZE * 2, PAGE_SIZE, PROT_READ | PROT_WRITE), Ok(0));
+    area[PAGE_SIZE * 2] = 42;
+    println!("mprotect ok");
+
+    // an untouched PROT_EXEC page is faulted in by the first fetch, and all zeros is an illegal
+    // instruction. A page without PROT_EXEC can't be run at all
+    let code_page = mmap(0, PAGE_SIZE, PROT_READ | PROT_EXEC).unwrap();
+    let run = |entry| unsafe { core::mem::transmute::<usize, fn()>(entry)() };
+    assert_eq!(in_child(run, code_page), -(SIGILL as i32));
+    assert_eq!(in_child(run, addr + PAGE_SIZE * 2), -(SIGSEGV as i32));
+    assert_eq!(munmap(code_page, PAGE_SIZE), Ok(0));
+    println!("exec fault ok");
+
+    assert_eq!(munmap(addr, PAGE_SIZE), Ok(0));
+    assert_eq!(munmap(addr + PAGE_SIZE * 2, PAGE_SIZE * 2), Ok(0));
+    println!("mmap test passed!");
+    0
+}
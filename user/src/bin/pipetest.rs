@@ -0,0 +1,96 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, exit, fork, pipe, read, wait, waitpid, write, SysError};
+
+const STR: &str = "Hello, world!";
+/// larger than the ring buffer in kernel, so that both ends have to block
+const LARGE: usize = 4096 * 4;
+
+fn byte_at(i: usize) -> u8 {
+    (i % 251) as u8
+}
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    // a short message and EOF
+    let (read_fd, write_fd) = pipe().unwrap();
+    let pid = fork().unwrap();
+    if pid == 0 {
+        close(write_fd).unwrap();
+        let mut buffer = [0u8; 32];
+        let len = read(read_fd, &mut buffer).unwrap();
+        assert_eq!(core::str::from_utf8(&buffer[..len]).unwrap(), STR);
+        // the parent has closed its write end
+        assert_eq!(read(read_fd, &mut buffer), Ok(0));
+        exit(0);
+    }
+    close(read_fd).unwrap();
+    assert_eq!(write(write_fd, STR.as_bytes()), Ok(STR.len()));
+    close(write_fd).unwrap();
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), Ok(pid));
+    assert_eq!(exit_code, 0);
+    println!("read and EOF ok");
+
+    // a producer -> filter -> consumer chain, the filter adds 1 to every byte
+    let (to_filter, from_producer) = pipe().unwrap();
+    let (to_consumer, from_filter) = pipe().unwrap();
+    if fork().unwrap() == 0 {
+        close(to_filter).unwrap();
+        close(to_consumer).unwrap();
+        close(from_filter).unwrap();
+        let data: [u8; 256] = core::array::from_fn(byte_at);
+        for _ in 0..LARGE / 256 {
+            assert_eq!(write(from_producer, &data), Ok(256));
+        }
+        exit(0);
+    }
+    if fork().unwrap() == 0 {
+        close(from_producer).unwrap();
+        close(to_consumer).unwrap();
+        let mut buffer = [0u8; 100];
+        loop {
+            let len = read(to_filter, &mut buffer).unwrap();
+            if len == 0 {
+                break;
+            }
+            buffer[..len].iter_mut().for_each(|b| *b = b.wrapping_add(1));
+            assert_eq!(write(from_filter, &buffer[..len]), Ok(len));
+        }
+        exit(0);
+    }
+    close(to_filter).unwrap();
+    close(from_producer).unwrap();
+    close(from_filter).unwrap();
+    let mut received = 0;
+    let mut buffer = [0u8; 333];
+    loop {
+        let len = read(to_consumer, &mut buffer).unwrap();
+        if len == 0 {
+            break;
+        }
+        for (i, &b) in buffer[..len].iter().enumerate() {
+            assert_eq!(b, byte_at((received + i) % 256).wrapping_add(1));
+        }
+        received += len;
+    }
+    assert_eq!(received, LARGE);
+    for _ in 0..2 {
+        wait(&mut exit_code).unwrap();
+        assert_eq!(exit_code, 0);
+    }
+    close(to_consumer).unwrap();
+    println!("pipe chain ok");
+
+    // nobody is reading
+    let (read_fd, write_fd) = pipe().unwrap();
+    close(read_fd).unwrap();
+    assert_eq!(write(write_fd, STR.as_bytes()), Err(SysError::EPIPE));
+    close(write_fd).unwrap();
+    println!("pipetest passed!");
+    0
+}
@@ -0,0 +1,37 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::vec;
+use user_lib::{ioctl, read, STDIN, TCGETCANON, TCSETCANON};
+
+const PAGE_SIZE: usize = 0x1000;
+const LINE_MAX: usize = 256;
+
+/// read stdin line by line in canonical mode until an empty line,
+/// the buffer starts right before a page boundary so that every line crosses it
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    ioctl(STDIN, TCSETCANON, 1).unwrap();
+    assert_eq!(ioctl(STDIN, TCGETCANON, 0), Ok(1));
+    let mut buffer = vec![0u8; PAGE_SIZE + LINE_MAX];
+    let start = PAGE_SIZE - (buffer.as_ptr() as usize + 8) % PAGE_SIZE;
+    let line = &mut buffer[start..start + LINE_MAX];
+    println!("type some lines, they are shown after Enter, an empty line quits");
+    loop {
+        let len = read(STDIN, line).unwrap();
+        // a line longer than the buffer is returned in pieces without '\n'
+        let text = line[..len].strip_suffix(b"\n").unwrap_or(&line[..len]);
+        if text.is_empty() {
+            break;
+        }
+        let text = core::str::from_utf8(text).unwrap_or("<not utf-8>");
+        println!("{} bytes: {}", len, text);
+    }
+    ioctl(STDIN, TCSETCANON, 0).unwrap();
+    println!("read_lines passed!");
+    0
+}
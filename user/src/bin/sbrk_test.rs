@@ -0,0 +1,75 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, mmap, munmap, sbrk, waitpid, SysError, PROT_READ};
+
+const PAGE_SIZE: usize = 0x1000;
+const MMAP_BASE: usize = 0x10_0000_0000;
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    println!("Test sbrk start.");
+    let origin_brk = sbrk(0).unwrap();
+    println!("origin break point = {:#x}", origin_brk);
+    assert_eq!(sbrk(PAGE_SIZE as i32), Ok(origin_brk));
+    let brk = sbrk(0).unwrap();
+    println!("one page allocated, break point = {:#x}", brk);
+    assert_eq!(brk, origin_brk + PAGE_SIZE);
+
+    println!("try write to allocated page");
+    let new_page =
+        unsafe { core::slice::from_raw_parts_mut(origin_brk as *mut u8, PAGE_SIZE) };
+    new_page.fill(1);
+    println!("write ok");
+
+    sbrk(PAGE_SIZE as i32 * 10).unwrap();
+    let brk = sbrk(0).unwrap();
+    println!("10 pages allocated, break point = {:#x}", brk);
+    // the last page of a large heap is only backed by a frame when it's touched
+    let last_byte = (brk - 1) as *mut u8;
+    unsafe {
+        last_byte.write_volatile(7);
+    }
+
+    // the heap should be inherited by the child
+    let pid = fork().unwrap();
+    if pid == 0 {
+        assert_eq!(new_page[PAGE_SIZE - 1], 1);
+        assert_eq!(unsafe { last_byte.read_volatile() }, 7);
+        new_page[0] = 2;
+        exit(0);
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), Ok(pid));
+    assert_eq!(exit_code, 0);
+    // child's writes are private to the child
+    assert_eq!(new_page[0], 1);
+
+    sbrk(PAGE_SIZE as i32 * -11).unwrap();
+    let brk = sbrk(0).unwrap();
+    println!("11 pages deallocated, break point = {:#x}", brk);
+    assert_eq!(brk, origin_brk);
+
+    // shrinking below the heap bottom should fail
+    assert_eq!(sbrk(-(PAGE_SIZE as i32)), Err(SysError::ENOMEM));
+
+    // growing into the mmap window should fail too, the heap is lazily mapped so it's cheap
+    const STEP: i32 = 0x4000_0000;
+    let mut steps = 0;
+    while sbrk(STEP).is_ok() {
+        steps += 1;
+    }
+    assert!(sbrk(0).unwrap() <= MMAP_BASE);
+    assert_eq!(mmap(MMAP_BASE, PAGE_SIZE, PROT_READ), Ok(MMAP_BASE));
+    assert_eq!(munmap(MMAP_BASE, PAGE_SIZE), Ok(0));
+    for _ in 0..steps {
+        sbrk(-STEP).unwrap();
+    }
+    assert_eq!(sbrk(0), Ok(origin_brk));
+    println!("heap limit ok");
+    println!("sbrk test passed!");
+    0
+}
@@ -0,0 +1,63 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    exit, fork, get_time, sleep, wait,
+    workload::{spin_until, work_unit, Arr, N},
+};
+
+const HOGS: usize = 4;
+const JOB_MS: usize = 30;
+
+/// run `units` work units in a new task and return how long it took in ms
+fn timed_job(units: i32) -> i32 {
+    if fork().unwrap() == 0 {
+        let start = get_time().unwrap();
+        let mut a: Arr = [[1; N]; N];
+        let b: Arr = [[1; N]; N];
+        for _ in 0..units {
+            work_unit(&mut a, &b);
+        }
+        exit((get_time().unwrap() - start) as i32);
+    }
+    let mut elapsed = 0;
+    wait(&mut elapsed).unwrap();
+    elapsed
+}
+
+/// only meaningful when the kernel is built with `SCHED=mlfq`,
+/// a short new job should finish nearly as fast as it does alone while CPU hogs are running
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    // calibrate the size of the job when running alone
+    let units = if fork().unwrap() == 0 {
+        exit(spin_until(get_time().unwrap() + JOB_MS));
+    } else {
+        let mut units = 0;
+        wait(&mut units).unwrap();
+        units
+    };
+    let alone = timed_job(units);
+
+    let end = get_time().unwrap() + 2000;
+    for _ in 0..HOGS {
+        if fork().unwrap() == 0 {
+            spin_until(end);
+            exit(0);
+        }
+    }
+    // let the hogs sink to the lowest level
+    sleep(500);
+    let contended = timed_job(units);
+    println!("the job takes {}ms alone, {}ms with {} hogs", alone, contended, HOGS);
+    for _ in 0..HOGS {
+        wait(&mut 0).unwrap();
+    }
+    // round-robin would take about (HOGS + 1) times as long
+    assert!(contended < alone * 2 + 20, "short job isn't favoured");
+    println!("sched_mlfq passed!");
+    0
+}
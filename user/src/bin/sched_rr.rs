@@ -0,0 +1,31 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, get_time, wait, workload::spin_until};
+
+const NUM: usize = 4;
+const RUN_MS: usize = 2000;
+
+/// tasks of the same priority should get the same share of CPU under every policy
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    let end = get_time().unwrap() + RUN_MS;
+    for _ in 0..NUM {
+        if fork().unwrap() == 0 {
+            exit(spin_until(end));
+        }
+    }
+    let mut counts = [0; NUM];
+    for count in counts.iter_mut() {
+        assert!(wait(count).is_ok());
+    }
+    println!("work done by each task: {:?}", counts);
+    let max = *counts.iter().max().unwrap();
+    let min = *counts.iter().min().unwrap();
+    assert!(min > 0 && max * 2 < min * 3, "unfair share");
+    println!("sched_rr passed!");
+    0
+}
@@ -0,0 +1,41 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, get_time, set_priority, wait, workload::spin_until, SysError};
+
+const RUN_MS: usize = 3000;
+
+/// only meaningful when the kernel is built with `SCHED=stride`,
+/// the work done by each task should be proportional to its priority
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    assert_eq!(set_priority(1), Err(SysError::EINVAL));
+    assert_eq!(set_priority((1 << 20) + 1), Err(SysError::EINVAL));
+    let end = get_time().unwrap() + RUN_MS;
+    let mut children = [(0usize, 0isize); 6];
+    for (i, child) in children.iter_mut().enumerate() {
+        let prio = i as isize + 5;
+        let pid = fork().unwrap();
+        if pid == 0 {
+            set_priority(prio).unwrap();
+            exit(spin_until(end));
+        }
+        *child = (pid, prio);
+    }
+    let mut ratios = [0; 6];
+    for _ in 0..children.len() {
+        let mut count = 0;
+        let pid = wait(&mut count).unwrap();
+        let (i, (_, prio)) = children.iter().enumerate().find(|(_, c)| c.0 == pid).unwrap();
+        println!("priority {}: {} work units", prio, count);
+        ratios[i] = count as isize / prio;
+    }
+    let max = *ratios.iter().max().unwrap();
+    let min = *ratios.iter().min().unwrap();
+    assert!(min > 0 && max * 2 < min * 3, "share isn't proportional to priority");
+    println!("sched_stride passed!");
+    0
+}
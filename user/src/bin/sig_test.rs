@@ -0,0 +1,126 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use user_lib::{
+    close, exit, fork, getpid, kill, pipe, read, sigaction, sigmask, sigprocmask, sleep, waitpid,
+    write, SignalAction, SysError, SIGINT, SIGKILL, SIGSEGV, SIGTERM, SIGUSR1, SIG_BLOCK, SIG_DFL,
+    SIG_IGN, SIG_SETMASK, SIG_UNBLOCK,
+};
+
+/// the last signal the handler got, 0 if none
+static CAUGHT: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn on_signal(signum: usize) {
+    CAUGHT.store(signum, Ordering::SeqCst);
+}
+
+fn wait_child(pid: usize) -> i32 {
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), Ok(pid));
+    exit_code
+}
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    let pid = getpid().unwrap();
+
+    // a signal sent to itself is delivered on the way back from kill
+    let old = sigaction(SIGUSR1, &SignalAction::new(on_signal as usize, 0)).unwrap();
+    assert_eq!(old.handler, SIG_DFL);
+    let mut sum = 0;
+    for i in 0..10 {
+        sum += i;
+        assert_eq!(kill(pid, SIGUSR1), Ok(0));
+        assert_eq!(CAUGHT.swap(0, Ordering::SeqCst), SIGUSR1);
+    }
+    // the registers of the interrupted code are restored
+    assert_eq!(sum, 45);
+    println!("handler ok");
+
+    // a blocked signal stays pending until it's unblocked
+    assert_eq!(sigprocmask(SIG_BLOCK, sigmask(SIGUSR1)), Ok(0));
+    kill(pid, SIGUSR1).unwrap();
+    assert_eq!(CAUGHT.load(Ordering::SeqCst), 0);
+    assert_eq!(sigprocmask(SIG_UNBLOCK, sigmask(SIGUSR1)), Ok(sigmask(SIGUSR1)));
+    assert_eq!(CAUGHT.swap(0, Ordering::SeqCst), SIGUSR1);
+    // SIGKILL can't be blocked or caught
+    assert_eq!(sigprocmask(SIG_SETMASK, sigmask(SIGKILL)), Ok(0));
+    assert_eq!(sigprocmask(SIG_SETMASK, 0), Ok(0));
+    assert_eq!(sigaction(SIGKILL, &SignalAction::new(SIG_IGN, 0)), Err(SysError::EINVAL));
+    println!("mask ok");
+
+    // the default action terminates the process, a sleeping one is woken up for it
+    let child = fork().unwrap();
+    if child == 0 {
+        sleep(100_000);
+        exit(0);
+    }
+    assert_eq!(kill(child, SIGTERM), Ok(0));
+    assert_eq!(wait_child(child), -(SIGTERM as i32));
+
+    // an ignored signal is discarded, SIGKILL still works
+    let (read_fd, write_fd) = pipe().unwrap();
+    let child = fork().unwrap();
+    if child == 0 {
+        sigaction(SIGINT, &SignalAction::new(SIG_IGN, 0)).unwrap();
+        kill(getpid().unwrap(), SIGINT).unwrap();
+        write(write_fd, b"x").unwrap();
+        loop {
+            sleep(10);
+        }
+    }
+    // wait until the child ignores SIGINT
+    let mut byte = [0u8; 1];
+    assert_eq!(read(read_fd, &mut byte), Ok(1));
+    close(read_fd).unwrap();
+    close(write_fd).unwrap();
+    assert_eq!(kill(child, SIGINT), Ok(0));
+    assert_eq!(kill(child, 0), Ok(0));
+    assert_eq!(kill(child, SIGKILL), Ok(0));
+    assert_eq!(wait_child(child), -(SIGKILL as i32));
+    assert_eq!(kill(child, 0), Err(SysError::ESRCH));
+    println!("kill ok");
+
+    // a caught signal interrupts a blocking read, the pipe can be closed right after it
+    sigaction(SIGINT, &SignalAction::new(on_signal as usize, 0)).unwrap();
+    let (read_fd, write_fd) = pipe().unwrap();
+    let child = fork().unwrap();
+    if child == 0 {
+        // the read may not have blocked yet when a signal comes
+        loop {
+            sleep(10);
+            kill(pid, SIGINT).unwrap();
+        }
+    }
+    assert_eq!(read(read_fd, &mut byte), Err(SysError::EINTR));
+    assert_eq!(CAUGHT.swap(0, Ordering::SeqCst), SIGINT);
+    // the child keeps sending it, which would interrupt waitpid
+    sigaction(SIGINT, &SignalAction::new(SIG_IGN, 0)).unwrap();
+    close(write_fd).unwrap();
+    close(read_fd).unwrap();
+    assert_eq!(kill(child, SIGKILL), Ok(0));
+    assert_eq!(wait_child(child), -(SIGKILL as i32));
+    sigaction(SIGINT, &SignalAction::new(SIG_DFL, 0)).unwrap();
+    println!("interrupt ok");
+
+    // a fault raises SIGSEGV, the child exits in its handler
+    let child = fork().unwrap();
+    if child == 0 {
+        extern "C" fn on_segv(signum: usize) {
+            exit(signum as i32);
+        }
+        sigaction(SIGSEGV, &SignalAction::new(on_segv as usize, 0)).unwrap();
+        unsafe { (0usize as *mut u8).write_volatile(0) };
+        exit(0);
+    }
+    assert_eq!(wait_child(child), SIGSEGV as i32);
+    println!("fault ok");
+
+    println!("sig_test passed!");
+    0
+}
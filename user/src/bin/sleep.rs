@@ -17,14 +17,16 @@ fn sleepy() {
 
 #[unsafe(no_mangle)]
 pub fn main() -> i32 {
-    let current_time = get_time();
-    let pid = fork();
+    let current_time = get_time().unwrap();
+    let pid = fork().unwrap();
     let mut exit_code: i32 = 0;
     if pid == 0 {
         sleepy();
     }
-    assert!(waitpid(pid as usize, &mut exit_code) == pid && exit_code == 0);
-    println!("use {} msecs.", get_time() - current_time);
+    assert!(waitpid(pid, &mut exit_code) == Ok(pid) && exit_code == 0);
+    let used = get_time().unwrap() - current_time;
+    println!("use {} msecs.", used);
+    assert!(used >= 500);
     println!("sleep pass.");
     0
 }
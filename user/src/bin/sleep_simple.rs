@@ -4,20 +4,23 @@
 #[macro_use]
 extern crate user_lib;
 
-use user_lib::{get_time, sleep};
+use user_lib::{get_time, nanosleep, sleep, SysError, TimeSpec};
 
 #[unsafe(no_mangle)]
 pub fn main() -> i32 {
     println!("into sleep test!");
-    let start = get_time();
+    let start = get_time().unwrap();
     println!("current time_msec = {}", start);
     sleep(100);
-    let end = get_time();
+    let end = get_time().unwrap();
     println!(
         "time_msec = {} after sleeping 100 ticks, delta = {}ms!",
         end,
         end - start
     );
+    assert!(end - start >= 100);
+    let bad_req = TimeSpec { sec: 0, nsec: 1_000_000_000 };
+    assert_eq!(nanosleep(&bad_req), Err(SysError::EINVAL));
     println!("r_sleep passed!");
     0
 }
\ No newline at end of file
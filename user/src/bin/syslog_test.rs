@@ -0,0 +1,41 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::vec;
+use user_lib::{getpid, set_log_level, syslog_clear, syslog_read, syslog_size, trace, SysError, LOG_INFO, LOG_OFF};
+
+/// whether the newest 4 KiB of the kernel log contain `pattern`
+fn log_contains(pattern: &[u8]) -> bool {
+    let mut buffer = vec![0u8; 4096];
+    let n = syslog_read(&mut buffer).unwrap();
+    buffer[..n].windows(pattern.len()).any(|window| window == pattern)
+}
+
+/// the "[strace]" lines of the kernel are used as records we know the content of
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    assert_eq!(set_log_level(6), Err(SysError::EINVAL));
+    let old_level = set_log_level(LOG_INFO).unwrap();
+
+    trace(true).unwrap();
+    getpid().unwrap();
+    trace(false).unwrap();
+    assert!(syslog_size().unwrap() > 0);
+    assert!(log_contains(b"getpid() ="));
+
+    // nothing is recorded above the max level
+    assert_eq!(set_log_level(LOG_OFF), Ok(LOG_INFO));
+    syslog_clear().unwrap();
+    trace(true).unwrap();
+    getpid().unwrap();
+    trace(false).unwrap();
+    assert!(!log_contains(b"getpid() ="));
+
+    set_log_level(old_level).unwrap();
+    println!("syslog_test passed!");
+    0
+}
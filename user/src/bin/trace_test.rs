@@ -0,0 +1,27 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, getpid, trace, waitpid};
+
+/// the syscalls below show up as "[strace]" lines in the kernel log
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    assert_eq!(trace(true), Ok(false));
+    println!("traced getpid: {}", getpid().unwrap());
+    let pid = fork().unwrap();
+    if pid == 0 {
+        // the child inherits the flag
+        assert_eq!(trace(false), Ok(true));
+        exit(0);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), Ok(pid));
+    assert_eq!(exit_code, 0);
+    assert_eq!(trace(false), Ok(true));
+    assert_eq!(trace(false), Ok(false));
+    println!("trace_test passed!");
+    0
+}
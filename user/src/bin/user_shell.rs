@@ -2,8 +2,8 @@
 #![no_main]
 #![allow(clippy::println_empty_string)]
 
-use alloc::string::String;
-use user_lib::{console::getchar, exec, fork, waitpid};
+use alloc::{string::String, vec::Vec};
+use user_lib::{close, console::getchar, dup2, exec, exit, fork, ioctl, pipe, trace, waitpid, STDIN, STDOUT, TCSETFG};
 
 extern crate alloc;
 
@@ -14,6 +14,108 @@ const LF: u8 = 0x0au8;
 const CR: u8 = 0x0du8;
 const DL: u8 = 0x7fu8;
 const BS: u8 = 0x08u8;
+/// Ctrl-C, the kernel sends SIGINT instead while a command runs
+const ETX: u8 = 0x03u8;
+
+/// one command of a pipeline, every argument ends with '\0'
+struct ProcessArguments {
+    args: Vec<String>,
+    args_addr: Vec<*const u8>,
+}
+
+impl ProcessArguments {
+    fn new(command: &str) -> Self {
+        let args: Vec<String> = command
+            .split_whitespace()
+            .map(|arg| {
+                let mut arg = String::from(arg);
+                arg.push('\0');
+                arg
+            })
+            .collect();
+        let mut args_addr: Vec<*const u8> = args.iter().map(|arg| arg.as_ptr()).collect();
+        args_addr.push(core::ptr::null());
+        Self { args, args_addr }
+    }
+}
+
+/// close both ends of every pipe
+fn close_pipes(pipes: &[(usize, usize)]) {
+    for &(read_fd, write_fd) in pipes.iter() {
+        close(read_fd).unwrap();
+        close(write_fd).unwrap();
+    }
+}
+
+/// run `a | b | ...`, the stdout of each command is connected to the stdin of the next one.
+/// The syscalls of the commands are logged by the kernel if `traced`
+fn run_pipeline(line: &str, traced: bool) {
+    let commands: Vec<ProcessArguments> = line.split('|').map(ProcessArguments::new).collect();
+    if commands.iter().any(|command| command.args.is_empty()) {
+        println!("Invalid command: empty command in pipeline");
+        return;
+    }
+    let mut pipes: Vec<(usize, usize)> = Vec::new();
+    for _ in 1..commands.len() {
+        match pipe() {
+            Ok(fds) => pipes.push(fds),
+            Err(err) => {
+                println!("Error when creating a pipe: {:?}", err);
+                close_pipes(&pipes);
+                return;
+            }
+        }
+    }
+    let mut children = Vec::new();
+    for (i, command) in commands.iter().enumerate() {
+        let pid = match fork() {
+            Ok(pid) => pid,
+            Err(err) => {
+                // the commands forked so far see their pipes closed and finish
+                println!("Error when forking: {:?}", err);
+                break;
+            }
+        };
+        if pid == 0 {
+            // if it's the sub-process
+            if i > 0 {
+                dup2(pipes[i - 1].0, STDIN).unwrap();
+            }
+            if i < pipes.len() {
+                dup2(pipes[i].1, STDOUT).unwrap();
+            }
+            // keep only the redirected stdin and stdout, or the readers never see EOF
+            close_pipes(&pipes);
+            if traced {
+                trace(true).unwrap();
+            }
+            if exec(
+                command.args[0].as_str(),
+                command.args_addr.as_slice(),
+                &[core::ptr::null()],
+            )
+            .is_err()
+            {
+                println!("Error when executing!");
+                exit(-4);
+            }
+            unreachable!();
+        }
+        // Ctrl-C interrupts the command forked last, the ones before it see the pipe closed then.
+        // It's set at once so that Ctrl-C works while the rest are being forked
+        ioctl(STDIN, TCSETFG, pid).unwrap();
+        children.push(pid);
+    }
+    close_pipes(&pipes);
+    for pid in children {
+        let mut exit_code: i32 = 0;
+        match waitpid(pid, &mut exit_code) {
+            Ok(_) => println!("Shell: Process {} exited with code {}", pid, exit_code),
+            Err(err) => println!("Shell: failed to wait for process {}: {:?}", pid, err),
+        }
+    }
+    ioctl(STDIN, TCSETFG, 0).unwrap();
+}
 
 #[unsafe(no_mangle)]
 pub fn main() -> i32 {
@@ -25,24 +127,20 @@ pub fn main() -> i32 {
         match c {
             LF | CR => {
                 println!("");
-                if !line.is_empty() {
-                    line.push('\0'); // as the symbol of ending
-                    let pid = fork();
-                    if pid == 0 {
-                        // if it's the sub-process
-                        if exec(line.as_str()) == -1 {
-                            println!("Error when executing!");
-                            return -4;
-                        }
-                        unreachable!();
-                    } else {
-                        let mut exit_code: i32 = 0;
-                        let exit_pid = waitpid(pid as usize, &mut exit_code);
-                        assert_eq!(pid, exit_pid);
-                        println!("Shell: Process {} exited with code {}", pid, exit_code);
-                    }
-                    line.clear();
+                let command = line.trim();
+                // builtin: strace <pipeline>
+                if let Some(pipeline) = command.strip_prefix("strace ") {
+                    run_pipeline(pipeline, true);
+                } else if !command.is_empty() {
+                    run_pipeline(command, false);
                 }
+                line.clear();
+                print!(">> ");
+            },
+            ETX => {
+                // drop the line being typed
+                println!("^C");
+                line.clear();
                 print!(">> ");
             },
             BS | DL => {
@@ -5,69 +5,67 @@
 extern crate user_lib;
 
 // not in SUCC_TESTS & FAIL_TESTS
-// count_lines, infloop, user_shell, usertests
+// count_lines, dmesg, infloop, read_lines, user_shell, usertests
+// sched_stride and sched_mlfq, run them by hand with the kernel built with SCHED=stride or SCHED=mlfq
 
 // item of TESTS : app_name(argv_0), argv_1, argv_2, argv_3, exit_code
 static SUCC_TESTS: &[(&str, &str, &str, &str, i32)] = &[
+    ("bad_address\0", "\0", "\0", "\0", 0),
+    ("cmdline_args\0", "aaa\0", "bbb\0", "ccc\0", 0),
+    ("exec_bad_elf\0", "\0", "\0", "\0", 0),
     ("exit\0", "\0", "\0", "\0", 0),
     ("fantastic_text\0", "\0", "\0", "\0", 0),
+    ("fd_test\0", "\0", "\0", "\0", 0),
+    ("filetest\0", "\0", "\0", "\0", 0),
+    ("fs_full\0", "\0", "\0", "\0", 0),
     ("forktest_simple\0", "\0", "\0", "\0", 0),
     ("forktest\0", "\0", "\0", "\0", 0),
     ("forktest2\0", "\0", "\0", "\0", 0),
     ("forktree\0", "\0", "\0", "\0", 0),
     ("hello_world\0", "\0", "\0", "\0", 0),
     ("matrix\0", "\0", "\0", "\0", 0),
+    ("mmap_test\0", "\0", "\0", "\0", 0),
+    ("pipetest\0", "\0", "\0", "\0", 0),
+    ("sbrk_test\0", "\0", "\0", "\0", 0),
+    ("sched_rr\0", "\0", "\0", "\0", 0),
     ("sleep_simple\0", "\0", "\0", "\0", 0),
     ("sleep\0", "\0", "\0", "\0", 0),
+    ("sig_test\0", "\0", "\0", "\0", 0),
+    ("syslog_test\0", "\0", "\0", "\0", 0),
+    ("trace_test\0", "\0", "\0", "\0", 0),
+    ("waitpid_block\0", "\0", "\0", "\0", 0),
     // ("yield\0", "\0", "\0", "\0", 0),
 ];
 
-static FAIL_TESTS: &[(&str, &str, &str, &str, i32)] = &[("stack_overflow\0", "\0", "\0", "\0", -2)];
+// a process killed by a signal exits with -signum, SIGSEGV here
+static FAIL_TESTS: &[(&str, &str, &str, &str, i32)] = &[("stack_overflow\0", "\0", "\0", "\0", -11)];
 
 use user_lib::{exec, fork, waitpid};
 
 fn run_tests(tests: &[(&str, &str, &str, &str, i32)]) -> i32 {
     let mut pass_num = 0;
-    let mut arr: [*const u8; 4] = [
-        core::ptr::null::<u8>(),
-        core::ptr::null::<u8>(),
-        core::ptr::null::<u8>(),
-        core::ptr::null::<u8>(),
-    ];
+    // argv ends with a null pointer
+    let mut arr: [*const u8; 5] = [core::ptr::null::<u8>(); 5];
 
     for test in tests {
         println!("Usertests: Running {}", test.0);
+        arr.fill(core::ptr::null::<u8>());
         arr[0] = test.0.as_ptr();
-        if test.1 != "\0" {
-            arr[1] = test.1.as_ptr();
-            arr[2] = core::ptr::null::<u8>();
-            arr[3] = core::ptr::null::<u8>();
-            if test.2 != "\0" {
-                arr[2] = test.2.as_ptr();
-                arr[3] = core::ptr::null::<u8>();
-                if test.3 != "\0" {
-                    arr[3] = test.3.as_ptr();
-                } else {
-                    arr[3] = core::ptr::null::<u8>();
-                }
-            } else {
-                arr[2] = core::ptr::null::<u8>();
-                arr[3] = core::ptr::null::<u8>();
+        for (slot, arg) in arr[1..4].iter_mut().zip([test.1, test.2, test.3]) {
+            if arg == "\0" {
+                break;
             }
-        } else {
-            arr[1] = core::ptr::null::<u8>();
-            arr[2] = core::ptr::null::<u8>();
-            arr[3] = core::ptr::null::<u8>();
+            *slot = arg.as_ptr();
         }
 
-        let pid = fork();
+        let pid = fork().unwrap();
         if pid == 0 {
-            exec(test.0);
+            let _ = exec(test.0, &arr, &[core::ptr::null::<u8>()]);
             panic!("unreachable!");
         } else {
             let mut exit_code: i32 = Default::default();
-            let wait_pid = waitpid(pid as usize, &mut exit_code);
-            assert_eq!(pid, wait_pid);
+            let wait_pid = waitpid(pid, &mut exit_code);
+            assert_eq!(wait_pid, Ok(pid));
             if exit_code == test.4 {
                 // summary apps with  exit_code
                 pass_num = pass_num + 1;
@@ -0,0 +1,32 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, getpid, sleep, wait, waitpid, SysError};
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    let slow = fork().unwrap();
+    if slow == 0 {
+        sleep(100);
+        exit(1);
+    }
+    let fast = fork().unwrap();
+    if fast == 0 {
+        exit(2);
+    }
+    println!("pid {}: forked slow child {} and fast child {}", getpid().unwrap(), slow, fast);
+
+    // the fast child exits first, the parent should go back to sleep until the slow one exits
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(slow, &mut exit_code), Ok(slow));
+    assert_eq!(exit_code, 1);
+    // the fast child is a zombie now, waiting for it returns at once
+    assert_eq!(wait(&mut exit_code), Ok(fast));
+    assert_eq!(exit_code, 2);
+    assert_eq!(wait(&mut exit_code), Err(SysError::ECHILD));
+    println!("waitpid block test passed!");
+    0
+}
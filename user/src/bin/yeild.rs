@@ -7,10 +7,10 @@ use user_lib::{getpid, yield_};
 
 #[unsafe(no_mangle)]
 pub fn main() -> i32 {
-    println!("Hello, I am process {}.", getpid());
+    println!("Hello, I am process {}.", getpid().unwrap());
     for i in 0..5 {
         yield_();
-        println!("Back in process {}, iteration {}.", getpid(), i);
+        println!("Back in process {}, iteration {}.", getpid().unwrap(), i);
     }
     println!("yield pass.");
     0
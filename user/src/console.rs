@@ -1,15 +1,12 @@
 use core::fmt::{self, Write};
 
-use crate::{read, write};
+use crate::{read, write, STDIN, STDOUT};
 
 struct Stdout;
 
-const STDIN: usize = 0;
-const STDOUT: usize = 1;
-
 impl Write for Stdout {
     fn write_str(&mut self, s: &str) -> fmt::Result {
-        write(STDOUT, s.as_bytes()); // fd = 1: write to console
+        write(STDOUT, s.as_bytes()).map_err(|_| fmt::Error)?; // fd = 1: write to console
         Ok(())
     }
 }
@@ -34,6 +31,6 @@ macro_rules! println {
 
 pub fn getchar() -> u8 {
     let mut c = [0u8; 1];
-    read(STDIN, &mut c);
+    read(STDIN, &mut c).unwrap();
     c[0]
 }
\ No newline at end of file
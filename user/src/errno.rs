@@ -0,0 +1,74 @@
+/// Error numbers returned by the kernel, compatible with Linux
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SysError {
+    EPERM,
+    ENOENT,
+    ESRCH,
+    EINTR,
+    EIO,
+    E2BIG,
+    ENOEXEC,
+    EBADF,
+    ECHILD,
+    EAGAIN,
+    ENOMEM,
+    EFAULT,
+    EEXIST,
+    ENODEV,
+    ENOTDIR,
+    EISDIR,
+    EINVAL,
+    EMFILE,
+    ENOTTY,
+    EFBIG,
+    ENOSPC,
+    EPIPE,
+    ENAMETOOLONG,
+    ENOSYS,
+    /// an errno this library doesn't know about
+    Unknown(isize),
+}
+
+/// Result of a syscall, Ok holds the (non-negative) return value
+pub type SysResult<T = usize> = Result<T, SysError>;
+
+impl SysError {
+    pub fn from_errno(errno: isize) -> Self {
+        match errno {
+            1 => Self::EPERM,
+            2 => Self::ENOENT,
+            3 => Self::ESRCH,
+            4 => Self::EINTR,
+            5 => Self::EIO,
+            7 => Self::E2BIG,
+            8 => Self::ENOEXEC,
+            9 => Self::EBADF,
+            10 => Self::ECHILD,
+            11 => Self::EAGAIN,
+            12 => Self::ENOMEM,
+            14 => Self::EFAULT,
+            17 => Self::EEXIST,
+            19 => Self::ENODEV,
+            20 => Self::ENOTDIR,
+            21 => Self::EISDIR,
+            22 => Self::EINVAL,
+            24 => Self::EMFILE,
+            25 => Self::ENOTTY,
+            27 => Self::EFBIG,
+            28 => Self::ENOSPC,
+            32 => Self::EPIPE,
+            36 => Self::ENAMETOOLONG,
+            38 => Self::ENOSYS,
+            _ => Self::Unknown(errno),
+        }
+    }
+}
+
+/// the kernel returns -errno on failure
+pub fn from_ret(ret: isize) -> SysResult {
+    if ret < 0 {
+        Err(SysError::from_errno(-ret))
+    } else {
+        Ok(ret as usize)
+    }
+}
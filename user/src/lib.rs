@@ -2,14 +2,106 @@
 #![feature(linkage)]
 #![feature(alloc_error_handler)]
 
+extern crate alloc;
+
+use alloc::vec::Vec;
 use buddy_system_allocator::LockedHeap;
-use syscall::{sys_exec, sys_exit, sys_fork, sys_get_time, sys_getpid, sys_read, sys_sbrk, sys_waitpid, sys_write, sys_yield};
+use errno::from_ret;
+pub use errno::{SysError, SysResult};
+use syscall::{sigreturn_trampoline, sys_close, sys_dup, sys_dup3, sys_exec, sys_ioctl, sys_kill, sys_sigaction, sys_sigprocmask, sys_pipe2, sys_exit, sys_fork, sys_get_time, sys_getpid, sys_mmap, sys_mprotect, sys_munmap, sys_nanosleep, sys_openat, sys_read, sys_sbrk, sys_set_priority, sys_syslog, sys_trace, sys_waitpid, sys_write, sys_yield};
 
 mod syscall;
 pub mod console;
+pub mod errno;
 mod lang_items;
+pub mod workload;
 
 const USER_HEAP_SIZE: usize = 16384;
+
+pub const PROT_READ: usize = 1 << 0;
+pub const PROT_WRITE: usize = 1 << 1;
+pub const PROT_EXEC: usize = 1 << 2;
+
+/// flags of open, the same values as Linux
+pub const O_RDONLY: u32 = 0;
+pub const O_WRONLY: u32 = 1 << 0;
+pub const O_RDWR: u32 = 1 << 1;
+pub const O_CREAT: u32 = 1 << 6;
+pub const O_TRUNC: u32 = 1 << 9;
+/// ioctl requests of the console, whether a read returns at the end of a line
+pub const TCGETCANON: usize = 0x5480;
+pub const TCSETCANON: usize = 0x5481;
+/// ioctl requests of the console, the pid Ctrl-C sends SIGINT to, 0 if there is none
+pub const TCGETFG: usize = 0x5482;
+pub const TCSETFG: usize = 0x5483;
+/// max levels of the kernel log, see `set_log_level`
+pub const LOG_OFF: usize = 0;
+pub const LOG_ERROR: usize = 1;
+pub const LOG_WARN: usize = 2;
+pub const LOG_INFO: usize = 3;
+pub const LOG_DEBUG: usize = 4;
+pub const LOG_TRACE: usize = 5;
+/// actions of syslog, the same values as Linux
+const SYSLOG_ACTION_READ_ALL: usize = 3;
+const SYSLOG_ACTION_CLEAR: usize = 5;
+const SYSLOG_ACTION_CONSOLE_LEVEL: usize = 8;
+const SYSLOG_ACTION_SIZE_UNREAD: usize = 9;
+/// signal numbers, the same values as Linux
+pub const SIGHUP: usize = 1;
+pub const SIGINT: usize = 2;
+pub const SIGQUIT: usize = 3;
+pub const SIGILL: usize = 4;
+pub const SIGABRT: usize = 6;
+pub const SIGKILL: usize = 9;
+pub const SIGUSR1: usize = 10;
+pub const SIGSEGV: usize = 11;
+pub const SIGUSR2: usize = 12;
+pub const SIGPIPE: usize = 13;
+pub const SIGALRM: usize = 14;
+pub const SIGTERM: usize = 15;
+pub const SIGCHLD: usize = 17;
+pub const SIGCONT: usize = 18;
+pub const SIGSTOP: usize = 19;
+/// handlers of sigaction besides a function
+pub const SIG_DFL: usize = 0;
+pub const SIG_IGN: usize = 1;
+/// how sigprocmask changes the blocked signals
+pub const SIG_BLOCK: usize = 0;
+pub const SIG_UNBLOCK: usize = 1;
+pub const SIG_SETMASK: usize = 2;
+/// resolve relative paths from the current directory
+const AT_FDCWD: isize = -100;
+/// the same layout as `struct timespec` in Linux
+#[repr(C)]
+pub struct TimeSpec {
+    pub sec: usize,
+    pub nsec: usize,
+}
+
+/// the layout of `struct sigaction` the kernel takes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(C)]
+pub struct SignalAction {
+    /// SIG_DFL, SIG_IGN or an `extern "C" fn(signum: usize)`
+    pub handler: usize,
+    pub flags: usize,
+    /// where the handler returns to, it calls sigreturn
+    pub restorer: usize,
+    /// blocked in addition to the signal itself while the handler runs
+    pub mask: u64,
+}
+
+impl SignalAction {
+    pub fn new(handler: usize, mask: u64) -> Self {
+        Self {
+            handler,
+            flags: 0,
+            restorer: sigreturn_trampoline(),
+            mask,
+        }
+    }
+}
+
 static mut HEAP_SPACE: [u8; USER_HEAP_SIZE] = [0; USER_HEAP_SIZE];
 
 #[global_allocator]
@@ -22,16 +114,27 @@ pub fn handle_alloc_error(layout: core::alloc::Layout) -> ! {
 
 #[unsafe(no_mangle)]
 #[unsafe(link_section = ".text.entry")]
-pub extern "C" fn _start() -> ! {
+pub extern "C" fn _start(argc: usize, argv: usize) -> ! {
     unsafe {
         HEAP.lock().init(&raw mut HEAP_SPACE as usize, USER_HEAP_SIZE);
     }
-    exit(main());
+    // the kernel has copied the argument strings onto the user stack, they live as long as the app
+    let argv = argv as *const usize;
+    let mut v: Vec<&'static str> = Vec::with_capacity(argc);
+    for i in 0..argc {
+        let str_start = unsafe { argv.add(i).read_volatile() } as *const u8;
+        let len = (0usize..)
+            .find(|&i| unsafe { str_start.add(i).read_volatile() } == 0)
+            .unwrap();
+        v.push(unsafe { core::str::from_utf8(core::slice::from_raw_parts(str_start, len)).unwrap() });
+    }
+    exit(main(argc, v.as_slice()));
 }
 
+/// apps that don't care about arguments can define `main() -> i32` instead
 #[linkage = "weak"]
 #[unsafe(no_mangle)]
-fn main() -> i32 {
+fn main(_argc: usize, _argv: &[&str]) -> i32 {
     panic!("Can't find main");
 }
 
@@ -46,12 +149,52 @@ fn main() -> i32 {
 //     });
 // }
 
-pub fn read(fd: usize, buffer: &mut [u8]) -> isize {
-    sys_read(fd, buffer)
+pub const STDIN: usize = 0;
+pub const STDOUT: usize = 1;
+pub const STDERR: usize = 2;
+
+/// open a file, `path` must end with '\0'
+pub fn open(path: &str, flags: u32) -> SysResult {
+    from_ret(sys_openat(AT_FDCWD, path, flags))
+}
+
+pub fn close(fd: usize) -> SysResult {
+    from_ret(sys_close(fd))
 }
 
-pub fn write(fd: usize, buffer: &[u8]) -> isize {
-    sys_write(fd, buffer)
+/// create a pipe, return its read fd and write fd
+pub fn pipe() -> SysResult<(usize, usize)> {
+    let mut fds = [0i32; 2];
+    from_ret(sys_pipe2(&mut fds, 0))?;
+    Ok((fds[0] as usize, fds[1] as usize))
+}
+
+/// duplicate `fd` to the lowest free fd
+pub fn dup(fd: usize) -> SysResult {
+    from_ret(sys_dup(fd))
+}
+
+/// duplicate `old_fd` to `new_fd`, `new_fd` is closed first if it's open
+pub fn dup2(old_fd: usize, new_fd: usize) -> SysResult {
+    if old_fd == new_fd {
+        // dup3 refuses it, while dup2 only checks that `old_fd` is open
+        close(dup(old_fd)?)?;
+        return Ok(new_fd);
+    }
+    from_ret(sys_dup3(old_fd, new_fd, 0))
+}
+
+/// device specific control, return ENOTTY if `fd` isn't the console
+pub fn ioctl(fd: usize, request: usize, arg: usize) -> SysResult {
+    from_ret(sys_ioctl(fd, request, arg))
+}
+
+pub fn read(fd: usize, buffer: &mut [u8]) -> SysResult {
+    from_ret(sys_read(fd, buffer))
+}
+
+pub fn write(fd: usize, buffer: &[u8]) -> SysResult {
+    from_ret(sys_write(fd, buffer))
 }
 
 pub fn exit(exit_code: i32) -> ! {
@@ -62,55 +205,116 @@ pub fn yield_() -> isize {
     sys_yield()
 }
 
-pub fn get_time() -> isize {
-    sys_get_time()
+/// only the stride scheduler makes use of it, `prio` must be at least 2
+pub fn set_priority(prio: isize) -> SysResult {
+    from_ret(sys_set_priority(prio))
 }
 
-pub fn sbrk(size: i32) -> isize {
-    sys_sbrk(size)
+/// the time since boot in ms
+pub fn get_time() -> SysResult {
+    from_ret(sys_get_time())
 }
 
-pub fn getpid() -> isize {
-    sys_getpid()
+/// move the program break by `size` bytes, return the old one
+pub fn sbrk(size: i32) -> SysResult {
+    from_ret(sys_sbrk(size))
 }
 
-pub fn fork() -> isize {
-    sys_fork()
+/// map an anonymous area, `start` = 0 lets the kernel choose the address
+pub fn mmap(start: usize, len: usize, prot: usize) -> SysResult {
+    from_ret(sys_mmap(start, len, prot))
 }
 
-pub fn exec(path: &str) -> isize {
-    sys_exec(path)
+pub fn munmap(start: usize, len: usize) -> SysResult {
+    from_ret(sys_munmap(start, len))
 }
 
-/// this function will wait all the sub-process
-pub fn wait(exit_code: &mut i32) -> isize {
-    loop {
-        match sys_waitpid(-1, exit_code as *mut _) {
-            -2 => {
-                // there is no finishing sub-process
-                yield_();
-            },
-            // -1 or other pid
-            exit_pid => return exit_pid,
-        }
-    }
+pub fn mprotect(start: usize, len: usize, prot: usize) -> SysResult {
+    from_ret(sys_mprotect(start, len, prot))
 }
 
-/// this function will wait the specific pid
-pub fn waitpid(pid: usize, exit_code: &mut i32) -> isize {
-    loop {
-        match sys_waitpid(pid as isize, exit_code as *mut _) {
-            -2 => {
-                yield_();
-            },
-            exit_pid => return exit_pid,
-        }
-    }
+pub fn getpid() -> SysResult {
+    from_ret(sys_getpid())
 }
 
+/// return 0 in the child and the pid of the child in the parent
+pub fn fork() -> SysResult {
+    from_ret(sys_fork())
+}
+
+/// only returns on failure, `path` and the strings in `args`/`envs` end with `\0`,
+/// `args` and `envs` end with a null pointer
+pub fn exec(path: &str, args: &[*const u8], envs: &[*const u8]) -> SysResult {
+    from_ret(sys_exec(path, args, envs))
+}
+
+/// wait for any sub-process to exit, the kernel blocks the caller until then
+pub fn wait(exit_code: &mut i32) -> SysResult {
+    from_ret(sys_waitpid(-1, exit_code as *mut _))
+}
+
+/// wait for the specific pid to exit
+pub fn waitpid(pid: usize, exit_code: &mut i32) -> SysResult {
+    from_ret(sys_waitpid(pid as isize, exit_code as *mut _))
+}
+
+/// block for the time given in `req`
+pub fn nanosleep(req: &TimeSpec) -> SysResult {
+    from_ret(sys_nanosleep(req))
+}
+
+/// block for `period_ms` milliseconds, the kernel wakes us up with its timer queue
 pub fn sleep(period_ms: usize) {
-    let start = sys_get_time();
-    while sys_get_time() < start + period_ms as isize {
-        sys_yield();
-    }
-}
\ No newline at end of file
+    let req = TimeSpec {
+        sec: period_ms / 1000,
+        nsec: period_ms % 1000 * 1_000_000,
+    };
+    nanosleep(&req).unwrap();
+}
+/// log the syscalls of the current process and its children in the kernel, return whether it was on
+pub fn trace(enable: bool) -> SysResult<bool> {
+    from_ret(sys_trace(enable as usize)).map(|old| old != 0)
+}
+
+/// copy the newest records of the kernel log into `buffer`, return the bytes copied
+pub fn syslog_read(buffer: &mut [u8]) -> SysResult {
+    from_ret(sys_syslog(SYSLOG_ACTION_READ_ALL, buffer.as_mut_ptr(), buffer.len()))
+}
+
+/// the number of bytes in the kernel log
+pub fn syslog_size() -> SysResult {
+    from_ret(sys_syslog(SYSLOG_ACTION_SIZE_UNREAD, core::ptr::null_mut(), 0))
+}
+
+pub fn syslog_clear() -> SysResult {
+    from_ret(sys_syslog(SYSLOG_ACTION_CLEAR, core::ptr::null_mut(), 0))
+}
+
+/// set the max level of the kernel log to one of the LOG_* values, return the previous one
+pub fn set_log_level(level: usize) -> SysResult {
+    from_ret(sys_syslog(SYSLOG_ACTION_CONSOLE_LEVEL, core::ptr::null_mut(), level))
+}
+
+/// the bit of `signum` in a set of signals
+pub fn sigmask(signum: usize) -> u64 {
+    1 << (signum - 1)
+}
+
+/// send `signum` to the process `pid`, 0 only checks that it exists
+pub fn kill(pid: usize, signum: usize) -> SysResult {
+    from_ret(sys_kill(pid, signum))
+}
+
+/// set the action of `signum`, return the old one
+pub fn sigaction(signum: usize, action: &SignalAction) -> SysResult<SignalAction> {
+    let mut old_action = SignalAction::new(SIG_DFL, 0);
+    from_ret(sys_sigaction(signum, action, &mut old_action))?;
+    Ok(old_action)
+}
+
+/// change the blocked signals with `set` as `how` says, return the old ones
+pub fn sigprocmask(how: usize, set: u64) -> SysResult<u64> {
+    let mut old_set = 0;
+    from_ret(sys_sigprocmask(how, &set, &mut old_set))?;
+    Ok(old_set)
+}
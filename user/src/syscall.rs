@@ -1,16 +1,52 @@
-use core::arch::asm;
+use core::arch::{asm, global_asm};
+
+use crate::{SignalAction, TimeSpec};
 
 const SYSCALL_WRITE: usize = 64;
 const SYSCALL_EXIT: usize = 93;
+const SYSCALL_NANOSLEEP: usize = 101;
+const SYSCALL_SYSLOG: usize = 116;
 const SYSCALL_YIELD: usize = 124;
+const SYSCALL_KILL: usize = 129;
+const SYSCALL_SIGACTION: usize = 134;
+const SYSCALL_SIGPROCMASK: usize = 135;
+const SYSCALL_SIGRETURN: usize = 139;
+const SYSCALL_SET_PRIORITY: usize = 140;
 const SYSCALL_GET_TIME: usize = 169;
 const SYSCALL_SBRK: usize = 214;
+const SYSCALL_MUNMAP: usize = 215;
+const SYSCALL_MMAP: usize = 222;
+const SYSCALL_MPROTECT: usize = 226;
 
+const SYSCALL_DUP: usize = 23;
+const SYSCALL_DUP3: usize = 24;
+const SYSCALL_IOCTL: usize = 29;
+const SYSCALL_OPENAT: usize = 56;
+const SYSCALL_CLOSE: usize = 57;
+const SYSCALL_PIPE2: usize = 59;
 const SYSCALL_READ: usize = 63;
 const SYSCALL_GETPID: usize = 172;
 const SYSCALL_FORK: usize = 220;
 const SYSCALL_EXEC: usize = 221;
 const SYSCALL_WAITPID: usize = 260;
+const SYSCALL_TRACE: usize = 1000;
+
+// signal handlers return here, the kernel restores the interrupted context in sigreturn
+global_asm!(
+    ".globl __sigreturn",
+    "__sigreturn:",
+    "li a7, {id}",
+    "ecall",
+    id = const SYSCALL_SIGRETURN,
+);
+
+/// the restorer of the signal handlers
+pub fn sigreturn_trampoline() -> usize {
+    unsafe extern "C" {
+        fn __sigreturn();
+    }
+    __sigreturn as usize
+}
 
 #[inline(always)]
 fn sys_call(eid: usize, args: [usize; 3]) -> isize {
@@ -27,6 +63,30 @@ fn sys_call(eid: usize, args: [usize; 3]) -> isize {
     ret
 }
 
+pub fn sys_dup(fd: usize) -> isize {
+    sys_call(SYSCALL_DUP, [fd, 0, 0])
+}
+
+pub fn sys_dup3(old_fd: usize, new_fd: usize, flags: usize) -> isize {
+    sys_call(SYSCALL_DUP3, [old_fd, new_fd, flags])
+}
+
+pub fn sys_ioctl(fd: usize, request: usize, arg: usize) -> isize {
+    sys_call(SYSCALL_IOCTL, [fd, request, arg])
+}
+
+pub fn sys_openat(dirfd: isize, path: &str, flags: u32) -> isize {
+    sys_call(SYSCALL_OPENAT, [dirfd as usize, path.as_ptr() as usize, flags as usize])
+}
+
+pub fn sys_close(fd: usize) -> isize {
+    sys_call(SYSCALL_CLOSE, [fd, 0, 0])
+}
+
+pub fn sys_pipe2(pipe: &mut [i32; 2], flags: usize) -> isize {
+    sys_call(SYSCALL_PIPE2, [pipe.as_mut_ptr() as usize, flags, 0])
+}
+
 pub fn sys_read(fd: usize, buffer: &mut [u8]) -> isize {
     sys_call(SYSCALL_READ, [fd, buffer.as_mut_ptr() as usize, buffer.len()])
 }
@@ -40,10 +100,18 @@ pub fn sys_exit(exit_code: i32) -> ! {
     panic!("sys_exit never returns!");
 }
 
+pub fn sys_nanosleep(req: &TimeSpec) -> isize {
+    sys_call(SYSCALL_NANOSLEEP, [req as *const _ as usize, 0, 0])
+}
+
 pub fn sys_yield() -> isize {
     sys_call(SYSCALL_YIELD, [0, 0, 0])
 }
 
+pub fn sys_set_priority(prio: isize) -> isize {
+    sys_call(SYSCALL_SET_PRIORITY, [prio as usize, 0, 0])
+}
+
 pub fn sys_get_time() -> isize {
     sys_call(SYSCALL_GET_TIME, [0, 0, 0])
 }
@@ -52,6 +120,18 @@ pub fn sys_sbrk(size: i32) -> isize {
     sys_call(SYSCALL_SBRK, [size as usize, 0, 0])
 }
 
+pub fn sys_mmap(start: usize, len: usize, prot: usize) -> isize {
+    sys_call(SYSCALL_MMAP, [start, len, prot])
+}
+
+pub fn sys_munmap(start: usize, len: usize) -> isize {
+    sys_call(SYSCALL_MUNMAP, [start, len, 0])
+}
+
+pub fn sys_mprotect(start: usize, len: usize, prot: usize) -> isize {
+    sys_call(SYSCALL_MPROTECT, [start, len, prot])
+}
+
 pub fn sys_getpid() -> isize {
     sys_call(SYSCALL_GETPID, [0, 0, 0])
 }
@@ -63,8 +143,11 @@ pub fn sys_fork() -> isize {
     sys_call(SYSCALL_FORK, [0, 0, 0])
 }
 
-pub fn sys_exec(path: &str) -> isize {
-    sys_call(SYSCALL_EXEC, [path.as_ptr() as usize, 0, 0])
+pub fn sys_exec(path: &str, args: &[*const u8], envs: &[*const u8]) -> isize {
+    sys_call(
+        SYSCALL_EXEC,
+        [path.as_ptr() as usize, args.as_ptr() as usize, envs.as_ptr() as usize],
+    )
 }
 
 /// 功能：当前进程等待一个子进程变为僵尸进程，回收其全部资源并收集其返回值。
@@ -75,4 +158,24 @@ pub fn sys_exec(path: &str) -> isize {
 /// syscall ID：260
 pub fn sys_waitpid(pid: isize, exit_code: *mut i32) -> isize {
     sys_call(SYSCALL_WAITPID, [pid as usize, exit_code as usize, 0])
-}
\ No newline at end of file
+}
+
+pub fn sys_trace(enable: usize) -> isize {
+    sys_call(SYSCALL_TRACE, [enable, 0, 0])
+}
+
+pub fn sys_syslog(action: usize, buf: *mut u8, len: usize) -> isize {
+    sys_call(SYSCALL_SYSLOG, [action, buf as usize, len])
+}
+
+pub fn sys_kill(pid: usize, signum: usize) -> isize {
+    sys_call(SYSCALL_KILL, [pid, signum, 0])
+}
+
+pub fn sys_sigaction(signum: usize, action: *const SignalAction, old_action: *mut SignalAction) -> isize {
+    sys_call(SYSCALL_SIGACTION, [signum, action as usize, old_action as usize])
+}
+
+pub fn sys_sigprocmask(how: usize, set: *const u64, old_set: *mut u64) -> isize {
+    sys_call(SYSCALL_SIGPROCMASK, [how, set as usize, old_set as usize])
+}
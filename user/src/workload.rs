@@ -0,0 +1,33 @@
+//! CPU-bound work for the scheduling tests, the same as `matrix` does
+
+use crate::get_time;
+
+/// the matrices are N x N
+pub const N: usize = 10;
+const P: i32 = 10007;
+pub type Arr = [[i32; N]; N];
+
+/// a piece of CPU-bound work
+pub fn work_unit(a: &mut Arr, b: &Arr) {
+    let mut c: Arr = Default::default();
+    for i in 0..N {
+        for j in 0..N {
+            for k in 0..N {
+                c[i][j] = (c[i][j] + a[i][k] * b[k][j]) % P;
+            }
+        }
+    }
+    *a = c;
+}
+
+/// count the work units done before `end`
+pub fn spin_until(end: usize) -> i32 {
+    let mut a: Arr = [[1; N]; N];
+    let b: Arr = [[1; N]; N];
+    let mut count = 0;
+    while get_time().unwrap() < end {
+        work_unit(&mut a, &b);
+        count += 1;
+    }
+    count
+}